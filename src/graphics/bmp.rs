@@ -0,0 +1,99 @@
+//! Uncompressed 24 bit BMP output
+//!
+//! Rows are stored from the bottom of the image up, each padded to a multiple of 4 bytes,
+//! with colors scaled down to 8 bits.
+
+use std::io::{self, prelude::Write};
+
+use crate::graphics::{utils::create_file, PPMImg};
+
+/// Size of the file header and the BITMAPINFOHEADER, after which the pixels start
+const HEADER_SIZE: u32 = 14 + 40;
+
+/// 72 dpi, in pixels per meter
+const RESOLUTION: u32 = 2835;
+
+impl PPMImg {
+    pub fn write_bmp(&self, filepath: &str) -> io::Result<()> {
+        self.write_bmp_to(&mut create_file(filepath)?)
+    }
+
+    /// Write the image to `out` as an uncompressed 24 bit BMP
+    pub fn write_bmp_to(&self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = self.output_size();
+        let row_size = (width as u64 * 3).div_ceil(4) * 4;
+        let image_size = row_size * height as u64;
+        if image_size + HEADER_SIZE as u64 > i32::MAX as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} by {} is too large for a BMP", width, height),
+            ));
+        }
+        let image_size = image_size as u32;
+
+        let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+        // file header
+        header.extend_from_slice(b"BM");
+        header.extend_from_slice(&(HEADER_SIZE + image_size).to_le_bytes());
+        header.extend_from_slice(&[0; 4]);
+        header.extend_from_slice(&HEADER_SIZE.to_le_bytes());
+        // BITMAPINFOHEADER, a positive height means the rows are bottom up
+        header.extend_from_slice(&40u32.to_le_bytes());
+        header.extend_from_slice(&(width as i32).to_le_bytes());
+        header.extend_from_slice(&(height as i32).to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&24u16.to_le_bytes());
+        // no compression
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&image_size.to_le_bytes());
+        header.extend_from_slice(&RESOLUTION.to_le_bytes());
+        header.extend_from_slice(&RESOLUTION.to_le_bytes());
+        // no palette
+        header.extend_from_slice(&[0; 8]);
+        out.write_all(&header)?;
+
+        let mut bytes = Vec::with_capacity(image_size as usize);
+        for mut row in self.bgr_rows_bottom_up() {
+            row.resize(row_size as usize, 0);
+            bytes.extend_from_slice(&row);
+        }
+        out.write_all(&bytes)?;
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphics::{Canvas, PPMImg, RGB};
+
+    #[test]
+    fn test_bmp() {
+        let mut img = PPMImg::new(2, 3, 100);
+        img.invert_y = true;
+        img.set_fg_color(RGB::new(100, 50, 0));
+        // bottom left, with y going up
+        img.plot(0, 0);
+        img.set_fg_color(RGB::new(0, 0, 100));
+        img.plot(2, 1);
+
+        let mut out = vec![];
+        img.write_bmp_to(&mut out).unwrap();
+        assert_eq!(b"BM", &out[..2]);
+        assert_eq!((out.len() as u32).to_le_bytes(), out[2..6]);
+        assert_eq!(54u32.to_le_bytes(), out[10..14]);
+        assert_eq!(3u32.to_le_bytes(), out[18..22]);
+        assert_eq!(2u32.to_le_bytes(), out[22..26]);
+        assert_eq!(24u16.to_le_bytes(), out[28..30]);
+
+        // 9 bytes of pixels per row, padded to 12, bottom row first
+        assert_eq!(54 + 2 * 12, out.len());
+        assert_eq!(
+            vec![0, 128, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            out[54..66].to_vec()
+        );
+        assert_eq!(
+            vec![0, 0, 0, 0, 0, 0, 255, 0, 0, 0, 0, 0],
+            out[66..].to_vec()
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Which triangles of a polygon matrix get drawn
+
+use crate::graphics::vector::Vec3;
+
+/// Faces that are skipped when rendering
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CullFace {
+    /// Skip faces pointing away from the viewer, for closed meshes
+    Back,
+    /// Skip faces pointing towards the viewer
+    Front,
+    /// Draw both sides, for open surfaces
+    None,
+}
+
+/// Order in which the vertices of a front face are listed, as seen from the front
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Winding {
+    CounterClockwise,
+    Clockwise,
+}
+
+/// Where the viewer is
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum View {
+    /// Infinitely far away, in the direction of the vector (from any surface towards the viewer)
+    Vector(Vec3),
+    /// At a point, for culling in world space with `Matrix::cull` before a perspective
+    /// projection. The canvas only sees device space, where the eye is no longer a point.
+    Eye(Vec3),
+}
+
+/// Which side of a triangle is seen
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Face {
+    Front,
+    Back,
+}
+
+/// How `Canvas::render_polygon_matrix` decides which triangles to draw
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Culling {
+    pub cull: CullFace,
+    pub winding: Winding,
+    pub view: View,
+}
+
+impl Culling {
+    pub fn new(cull: CullFace, winding: Winding, view: View) -> Self {
+        Culling {
+            cull,
+            winding,
+            view,
+        }
+    }
+
+    /// Normal of the front face of triangle (v0, v1, v2), not normalized
+    pub fn front_normal(&self, v0: Vec3, v1: Vec3, v2: Vec3) -> Vec3 {
+        let n = (v1 - v0).cross(v2 - v0);
+        match self.winding {
+            Winding::CounterClockwise => n,
+            Winding::Clockwise => -n,
+        }
+    }
+
+    /// Side of triangle (v0, v1, v2) that the viewer sees, or None if it is not drawn.
+    ///
+    /// Triangles seen edge-on are only drawn when nothing is culled.
+    pub fn facing(&self, v0: Vec3, v1: Vec3, v2: Vec3) -> Option<Face> {
+        let view = match self.view {
+            View::Vector(v) => v,
+            View::Eye(eye) => eye - v0,
+        };
+        let d = self.front_normal(v0, v1, v2).dot(view);
+        let face = if d > 0. {
+            Face::Front
+        } else if d < 0. {
+            Face::Back
+        } else if self.cull == CullFace::None {
+            Face::Front
+        } else {
+            return None;
+        };
+        match (self.cull, face) {
+            (CullFace::Back, Face::Back) | (CullFace::Front, Face::Front) => None,
+            _ => Some(face),
+        }
+    }
+}
+
+impl Default for Culling {
+    /// Back-face culling of counter-clockwise triangles, viewed from +z
+    fn default() -> Self {
+        Culling::new(
+            CullFace::Back,
+            Winding::CounterClockwise,
+            View::Vector(Vec3(0., 0., 1.)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_facing() {
+        let (v0, v1, v2) = (Vec3(0., 0., 0.), Vec3(1., 0., 0.), Vec3(0., 1., 0.));
+        let mut culling = Culling::default();
+        assert_eq!(Some(Face::Front), culling.facing(v0, v1, v2));
+        assert_eq!(None, culling.facing(v0, v2, v1));
+
+        culling.winding = Winding::Clockwise;
+        assert_eq!(None, culling.facing(v0, v1, v2));
+        assert_eq!(Some(Face::Front), culling.facing(v0, v2, v1));
+
+        culling.cull = CullFace::Front;
+        assert_eq!(Some(Face::Back), culling.facing(v0, v1, v2));
+        assert_eq!(None, culling.facing(v0, v2, v1));
+
+        culling.cull = CullFace::None;
+        assert_eq!(Some(Face::Back), culling.facing(v0, v1, v2));
+        assert_eq!(Some(Face::Front), culling.facing(v0, v2, v1));
+
+        // an eye below the plane sees the other side
+        let culling = Culling::new(
+            CullFace::Back,
+            Winding::CounterClockwise,
+            View::Eye(Vec3(5., 5., -1.)),
+        );
+        assert_eq!(None, culling.facing(v0, v1, v2));
+        assert_eq!(Some(Face::Front), culling.facing(v0, v2, v1));
+    }
+}
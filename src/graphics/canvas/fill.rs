@@ -0,0 +1,195 @@
+//! Filling arbitrary 2d polygons with scanlines
+
+use std::ops::RangeInclusive;
+
+/// Which points are inside of a polygon whose outline crosses itself or has holes,
+/// going by how many times the outline winds around them
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    /// Inside when a ray from the point crosses the outline an odd number of times
+    EvenOdd,
+    /// Inside when the outline winds around the point at all, counting clockwise and
+    /// counter clockwise turns against each other
+    NonZero,
+}
+
+impl FillRule {
+    fn is_inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::EvenOdd => winding % 2 != 0,
+            FillRule::NonZero => winding != 0,
+        }
+    }
+}
+
+/// Fill the polygon made of `rings` under `rule`, calling `plot` with every pixel (x, y) inside
+/// of it within (xmin, xmax, ymin, ymax).
+///
+/// Each ring is a list of (x, y) points that is closed back to its first point. A pixel is inside
+/// when its center is (see `centers_in`): every row y is crossed with the outline, and the
+/// crossings are sorted by x and summed up with +1 for edges going up and -1 for edges going down.
+pub(crate) fn scan_polygon(
+    rings: &[Vec<(f64, f64)>],
+    rule: FillRule,
+    (xmin, xmax, ymin, ymax): (i32, i32, i32, i32),
+    mut plot: impl FnMut(i32, i32),
+) {
+    // (x0, y0, x1, y1, direction) with y0 < y1
+    let mut edges = vec![];
+    for ring in rings {
+        for (i, &(xa, ya)) in ring.iter().enumerate() {
+            let (xb, yb) = ring[(i + 1) % ring.len()];
+            if ya < yb {
+                edges.push((xa, ya, xb, yb, 1));
+            } else if ya > yb {
+                edges.push((xb, yb, xa, ya, -1));
+            }
+            // horizontal edges never cross a row
+        }
+    }
+
+    if edges.is_empty() {
+        return;
+    }
+    let bottom = edges.iter().map(|e| e.1).fold(f64::INFINITY, f64::min);
+    let top = edges.iter().map(|e| e.3).fold(f64::NEG_INFINITY, f64::max);
+
+    let mut crossings: Vec<(f64, i32)> = vec![];
+    for y in centers_in(bottom, top, (ymin, ymax)) {
+        let yf = y as f64;
+        crossings.clear();
+        for &(x0, y0, x1, y1, direction) in edges.iter() {
+            if y0 <= yf && yf < y1 {
+                crossings.push((x0 + (yf - y0) * (x1 - x0) / (y1 - y0), direction));
+            }
+        }
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut winding = 0;
+        for pair in crossings.windows(2) {
+            winding += pair[0].1;
+            if rule.is_inside(winding) {
+                for x in centers_in(pair[0].0, pair[1].0, (xmin, xmax)) {
+                    plot(x, y);
+                }
+            }
+        }
+    }
+}
+
+/// The pixels from `lo` to `hi` within (min, max), which are the ones whose centers are in
+/// [lo, hi).
+///
+/// This is the coverage rule of every scanline fill, for rows as well as spans: shapes sharing
+/// an edge never both fill a pixel on it, and together they leave no gap along it.
+pub(crate) fn centers_in(lo: f64, hi: f64, (min, max): (i32, i32)) -> RangeInclusive<i32> {
+    (lo.ceil() as i32).max(min)..=(hi.ceil() as i32).saturating_sub(1).min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{
+        canvas::scan_triangle_in,
+        test_utils::{filled, lit},
+        vector::Vec3,
+        Canvas, Matrix, PPMImg,
+    };
+
+    fn square(x: f64, y: f64, size: f64) -> Vec<(f64, f64)> {
+        vec![(x, y), (x + size, y), (x + size, y + size), (x, y + size)]
+    }
+
+    #[test]
+    fn test_fill_polygon_holes() {
+        let outer = square(0., 0., 10.);
+        let hole = square(3., 3., 4.);
+        let mut reversed = hole.clone();
+        reversed.reverse();
+
+        for &(inner, rule, hollow) in [
+            (&hole, FillRule::EvenOdd, true),
+            (&hole, FillRule::NonZero, false),
+            (&reversed, FillRule::EvenOdd, true),
+            (&reversed, FillRule::NonZero, true),
+        ]
+        .iter()
+        {
+            let mut img = PPMImg::new(20, 20, 255);
+            img.fill_polygon(&[outer.clone(), inner.clone()], rule);
+            assert!(filled(&img, 0, 0) && filled(&img, 9, 9) && filled(&img, 2, 5));
+            assert!(!filled(&img, 10, 5) && !filled(&img, 5, 10));
+            assert_eq!(hollow, !filled(&img, 5, 5), "{:?}", rule);
+            assert_eq!(if hollow { 100 - 16 } else { 100 }, lit(&img).len());
+        }
+    }
+
+    #[test]
+    fn test_fill_polygon_matches_triangles() {
+        // a pentagon off the pixel grid, and the fan of triangles it splits into
+        let rings = vec![vec![
+            (10.3, 5.7),
+            (40.2, 12.5),
+            (45.5, 38.1),
+            (22.8, 47.4),
+            (4.6, 30.2),
+        ]];
+        let bounds = (0, 49, 0, 49);
+
+        let mut polygon = vec![vec![0; 50]; 50];
+        scan_polygon(&rings, FillRule::NonZero, bounds, |x, y| {
+            polygon[y as usize][x as usize] += 1
+        });
+        let (ring, mut triangles) = (&rings[0], vec![vec![0; 50]; 50]);
+        for i in 1..ring.len() - 1 {
+            let pts = [ring[0], ring[i], ring[i + 1]].map(|(x, y)| (x, y, 0.));
+            scan_triangle_in(pts, [Vec3(0., 0., 0.); 3], bounds, |x, y, _, _| {
+                triangles[y as usize][x as usize] += 1
+            });
+        }
+
+        // the same pixels, and none of them twice: no seams or overlaps between the triangles
+        assert_eq!(polygon, triangles);
+        assert!(triangles.iter().flatten().all(|&n| n <= 1));
+    }
+
+    #[test]
+    fn test_fill_polygon_self_intersecting() {
+        // a pentagram, its center is wound around twice
+        let star: Vec<(f64, f64)> = (0..5)
+            .map(|i| {
+                let angle = (90. + 144. * i as f64).to_radians();
+                (50. + 40. * angle.cos(), 50. + 40. * angle.sin())
+            })
+            .collect();
+        let rings = vec![star];
+
+        let mut even_odd = PPMImg::new(100, 100, 255);
+        even_odd.fill_polygon(&rings, FillRule::EvenOdd);
+        let mut nonzero = PPMImg::new(100, 100, 255);
+        nonzero.fill_polygon(&rings, FillRule::NonZero);
+
+        assert!(!filled(&even_odd, 50, 50));
+        assert!(filled(&nonzero, 50, 50));
+        // a tip of the star
+        assert!(filled(&even_odd, 50, 85) && filled(&nonzero, 50, 85));
+    }
+
+    #[test]
+    fn test_fill_edge_matrix() {
+        let mut m = Matrix::new_edge_matrix();
+        m.add_circle((50., 50., 0.), 30.);
+        // an open path is closed back to where it started
+        m.append_edge(&[0., 0., 0., 20., 0., 0.]);
+        m.append_edge(&[20., 0., 0., 20., 10., 0.]);
+
+        let mut img = PPMImg::new(100, 100, 255);
+        img.fill_edge_matrix(&m, FillRule::NonZero);
+
+        let area = std::f64::consts::PI * 30. * 30. + 20. * 10. / 2.;
+        let n = lit(&img).len();
+        assert!((n as f64 - area).abs() < 30., "{}", n);
+        assert!(filled(&img, 50, 50) && filled(&img, 15, 2));
+        assert!(!filled(&img, 5, 8));
+    }
+}
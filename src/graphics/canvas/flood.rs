@@ -0,0 +1,165 @@
+//! Flood filling with spans, for any canvas that can read its pixels back
+
+use super::Canvas;
+use crate::graphics::RGB;
+
+/// Which pixels a flood fill spreads over
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FloodMode {
+    /// Every pixel until one with the boundary color
+    Boundary(RGB),
+    /// Every pixel with the same color as the one the fill starts at
+    Seed,
+}
+
+/// Which neighbors of a pixel a flood fill spreads to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Left, right, up and down
+    Four,
+    /// The four sides and the four corners, so fills leak through diagonal gaps
+    Eight,
+}
+
+/// Flood fill `canvas` from (x, y), calling `fill` with every pixel that is reached.
+///
+/// Whole horizontal spans are filled at once. The rows above and below each span are then
+/// scanned for new spans, and one seed is pushed for each of them.
+///
+/// Only pixels on the canvas are filled, and each of them at most once, so `fill` may change
+/// a pixel to any color, even one the fill would spread over.
+pub(crate) fn flood<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    x: i32,
+    y: i32,
+    mode: FloodMode,
+    connectivity: Connectivity,
+    mut fill: impl FnMut(&mut C, i32, i32),
+) {
+    let (width, height) = (canvas.width() as i32, canvas.height() as i32);
+    let seed = match canvas.get_pixel(x, y) {
+        Some(color) => color,
+        None => return,
+    };
+    let mut filled = vec![false; (width * height) as usize];
+
+    let inside = |canvas: &C, filled: &[bool], x: i32, y: i32| {
+        if x < 0 || y < 0 || x >= width || y >= height || filled[(y * width + x) as usize] {
+            return false;
+        }
+        match (mode, canvas.get_pixel(x, y)) {
+            (FloodMode::Boundary(bound), Some(color)) => color != bound,
+            (FloodMode::Seed, Some(color)) => color == seed,
+            (_, None) => false,
+        }
+    };
+
+    let mut seeds = vec![(x, y)];
+    while let Some((x, y)) = seeds.pop() {
+        if !inside(canvas, &filled, x, y) {
+            continue;
+        }
+        let (mut left, mut right) = (x, x);
+        while inside(canvas, &filled, left - 1, y) {
+            left -= 1;
+        }
+        while inside(canvas, &filled, right + 1, y) {
+            right += 1;
+        }
+        for x in left..=right {
+            filled[(y * width + x) as usize] = true;
+            fill(canvas, x, y);
+        }
+
+        // diagonal neighbors are one pixel past the ends of the span
+        let (from, to) = match connectivity {
+            Connectivity::Four => (left, right),
+            Connectivity::Eight => (left - 1, right + 1),
+        };
+        for &ny in [y - 1, y + 1].iter() {
+            let mut in_span = false;
+            for nx in from..=to {
+                let next = inside(canvas, &filled, nx, ny);
+                if next && !in_span {
+                    seeds.push((nx, ny));
+                }
+                in_span = next;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{test_utils::count, PPMImg};
+
+    /// The outline of a 10 x 10 square with its corner at (5, 5), on a 30 x 30 image
+    fn outlined() -> PPMImg {
+        let mut img = PPMImg::new(30, 30, 255);
+        for i in 5..15 {
+            img.plot(i, 5);
+            img.plot(i, 14);
+            img.plot(5, i);
+            img.plot(14, i);
+        }
+        img
+    }
+
+    #[test]
+    fn test_boundary_fill() {
+        let (red, white) = (RGB::new(255, 0, 0), RGB::gray(255));
+        let mut img = outlined();
+        img.set_fg_color(red);
+        img.flood_fill(8, 8, FloodMode::Boundary(white), Connectivity::Four);
+        assert_eq!(64, count(&img, red));
+        assert_eq!(36, count(&img, white));
+
+        // the outside, in the boundary's color
+        img.set_fg_color(white);
+        img.flood_fill(0, 0, FloodMode::Boundary(white), Connectivity::Four);
+        assert_eq!(30 * 30 - 64, count(&img, white));
+    }
+
+    #[test]
+    fn test_seed_fill_connectivity() {
+        let (red, white) = (RGB::new(255, 0, 0), RGB::gray(255));
+        // a diagonal line of pixels, which only holds back 4-connected fills
+        let diagonal = || {
+            let mut img = PPMImg::new(10, 10, 255);
+            for i in 0..10 {
+                img.plot(i, i);
+            }
+            img
+        };
+
+        let mut four = diagonal();
+        four.set_fg_color(red);
+        four.flood_fill(9, 0, FloodMode::Seed, Connectivity::Four);
+        assert_eq!(45, count(&four, red));
+        assert_eq!(Some(RGB::gray(0)), four.get_pixel(0, 9));
+
+        let mut eight = diagonal();
+        eight.set_fg_color(red);
+        eight.flood_fill(9, 0, FloodMode::Seed, Connectivity::Eight);
+        assert_eq!(90, count(&eight, red));
+
+        // the line itself, with a fill color that matches what's being filled
+        eight.set_fg_color(white);
+        eight.flood_fill(0, 0, FloodMode::Seed, Connectivity::Eight);
+        assert_eq!(10, count(&eight, white));
+    }
+
+    #[test]
+    fn test_flood_fill_with_fn() {
+        let mut img = outlined();
+        img.flood_fill_with_fn(8, 8, FloodMode::Seed, Connectivity::Four, &|x, _| {
+            RGB::gray(x as u16 * 10)
+        });
+        assert_eq!(Some(RGB::gray(60)), img.get_pixel(6, 13));
+        assert_eq!(Some(RGB::gray(130)), img.get_pixel(13, 6));
+        assert_eq!(Some(RGB::gray(0)), img.get_pixel(20, 20));
+        // the fg color is left alone
+        assert_eq!(RGB::gray(255), img.get_fg_color());
+    }
+}
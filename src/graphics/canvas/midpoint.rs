@@ -0,0 +1,208 @@
+//! Midpoint circle and ellipse rasterization
+//!
+//! Only the points of one quadrant are computed, with integer arithmetic, and mirrored into
+//! the other three. Points on the axes are shared between quadrants and only plotted once.
+
+use super::Canvas;
+
+/// Points (x, y) with x, y >= 0 on the circle of radius `r` around the origin
+pub(crate) fn circle_quadrant(r: u32) -> Vec<(i32, i32)> {
+    // walk the octant from (r, 0) up to the diagonal, then mirror it
+    let (mut x, mut y) = (r as i64, 0i64);
+    let mut d = 1 - x;
+    let mut octant = vec![];
+    while x >= y {
+        octant.push((x as i32, y as i32));
+        y += 1;
+        if d < 0 {
+            d += 2 * y + 1;
+        } else {
+            x -= 1;
+            d += 2 * (y - x) + 1;
+        }
+    }
+
+    let mut points = octant.clone();
+    points.extend(
+        octant
+            .iter()
+            .rev()
+            .filter(|(x, y)| x != y)
+            .map(|&(x, y)| (y, x)),
+    );
+    points
+}
+
+/// Points (x, y) with x, y >= 0 on the axis aligned ellipse with radii `rx` and `ry`
+/// around the origin
+pub(crate) fn ellipse_quadrant(rx: u32, ry: u32) -> Vec<(i32, i32)> {
+    if ry == 0 {
+        return (0..=rx as i32).map(|x| (x, 0)).collect();
+    }
+    let (rx2, ry2) = (rx as i64 * rx as i64, ry as i64 * ry as i64);
+    let (mut x, mut y) = (0i64, ry as i64);
+    let (mut dx, mut dy) = (0, 2 * rx2 * y);
+    let mut points = vec![];
+
+    // decision variables are 4 times the value of the ellipse function at the midpoint,
+    // to keep them integers
+
+    // region 1, where the slope is > -1 and x steps every time
+    let mut d = 4 * ry2 - 4 * rx2 * y + rx2;
+    while dx < dy {
+        points.push((x as i32, y as i32));
+        x += 1;
+        dx += 2 * ry2;
+        if d < 0 {
+            d += 4 * (dx + ry2);
+        } else {
+            y -= 1;
+            dy -= 2 * rx2;
+            d += 4 * (dx - dy + ry2);
+        }
+    }
+
+    // region 2, where the slope is < -1 and y steps every time
+    let mut d = ry2 * (2 * x + 1) * (2 * x + 1) + 4 * rx2 * (y - 1) * (y - 1) - 4 * rx2 * ry2;
+    while y >= 0 {
+        points.push((x as i32, y as i32));
+        y -= 1;
+        dy -= 2 * rx2;
+        if d > 0 {
+            d += 4 * (rx2 - dy);
+        } else {
+            x += 1;
+            dx += 2 * ry2;
+            d += 4 * (dx - dy + rx2);
+        }
+    }
+    points
+}
+
+/// Plot `quadrant` mirrored into all four quadrants around (xc, yc)
+pub(crate) fn plot_quadrants<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    xc: i32,
+    yc: i32,
+    quadrant: &[(i32, i32)],
+) {
+    for &(x, y) in quadrant {
+        canvas.plot(xc + x, yc + y);
+        if x != 0 {
+            canvas.plot(xc - x, yc + y);
+        }
+        if y != 0 {
+            canvas.plot(xc + x, yc - y);
+            if x != 0 {
+                canvas.plot(xc - x, yc - y);
+            }
+        }
+    }
+}
+
+/// Fill the shape outlined by `quadrant` mirrored around (xc, yc), one row at a time
+pub(crate) fn fill_quadrants<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    xc: i32,
+    yc: i32,
+    quadrant: &[(i32, i32)],
+) {
+    // widest point on each row
+    let rows = quadrant.iter().map(|p| p.1).max().map_or(0, |y| y + 1);
+    let mut half_width = vec![-1; rows as usize];
+    for &(x, y) in quadrant {
+        half_width[y as usize] = half_width[y as usize].max(x);
+    }
+
+    for (y, &w) in half_width.iter().enumerate() {
+        let y = y as i32;
+        for x in -w..=w {
+            canvas.plot(xc + x, yc + y);
+            if y != 0 {
+                canvas.plot(xc + x, yc - y);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{test_utils::lit, PPMImg};
+
+    /// fg is drawn half transparent, so `lit` catches pixels that are plotted twice
+    fn new_img() -> PPMImg {
+        let mut img = PPMImg::new(101, 101, 100);
+        img.fg_alpha = 50;
+        img
+    }
+
+    #[test]
+    fn test_circle() {
+        let mut img = new_img();
+        img.draw_circle(50, 50, 1);
+        assert_eq!(vec![(50, 49), (49, 50), (51, 50), (50, 51)], lit(&img));
+
+        for &r in [0, 5, 17, 40].iter() {
+            let mut img = new_img();
+            img.draw_circle(50, 50, r);
+            let points = lit(&img);
+            for &(x, y) in points.iter() {
+                let (dx, dy) = ((x - 50) as f64, (y - 50) as f64);
+                assert!((dx.hypot(dy) - r as f64).abs() <= 0.5);
+                // no gaps, every point touches another one
+                let touches = |&(px, py): &(i32, i32)| {
+                    (px, py) != (x, y) && (px - x).abs() <= 1 && (py - y).abs() <= 1
+                };
+                assert!(r == 0 || points.iter().any(touches));
+            }
+            // mirror images of each other
+            assert!(points.iter().all(|&(x, y)| points.contains(&(y, x))));
+        }
+    }
+
+    #[test]
+    fn test_ellipse() {
+        let (rx, ry) = (40., 15.);
+        let mut img = new_img();
+        img.draw_ellipse(50, 50, rx as u32, ry as u32);
+        for (x, y) in lit(&img) {
+            let (dx, dy) = ((x - 50) as f64, (y - 50) as f64);
+            // distance from the ellipse, roughly in pixels
+            let f = (dx * dx / (rx * rx) + dy * dy / (ry * ry)).sqrt() - 1.;
+            assert!(f.abs() * ry <= 1., "({}, {}) is off the ellipse", x, y);
+        }
+
+        let mut img = new_img();
+        img.fill_ellipse(50, 50, rx as u32, ry as u32);
+        let area = std::f64::consts::PI * rx * ry;
+        assert!((lit(&img).len() as f64 - area).abs() / area < 0.05);
+
+        // flat ellipses are lines
+        let mut img = new_img();
+        img.draw_ellipse(50, 50, 3, 0);
+        img.fill_ellipse(50, 20, 0, 2);
+        assert_eq!(
+            vec![(50, 18), (50, 19), (50, 20), (50, 21), (50, 22)],
+            lit(&img)[..5].to_vec()
+        );
+        assert_eq!(12, lit(&img).len());
+    }
+
+    #[test]
+    fn test_fill_circle() {
+        let mut img = new_img();
+        img.fill_circle(50, 50, 1);
+        assert_eq!(5, lit(&img).len());
+
+        let mut img = new_img();
+        img.fill_circle(50, 50, 30);
+        let filled = lit(&img);
+        // the outline is part of the fill
+        let mut outline = new_img();
+        outline.draw_circle(50, 50, 30);
+        assert!(lit(&outline).iter().all(|p| filled.contains(p)));
+        let area = std::f64::consts::PI * 30. * 30.;
+        assert!((filled.len() as f64 - area).abs() / area < 0.05);
+    }
+}
@@ -1,119 +1,282 @@
+use self::{
+    fill::{centers_in, scan_polygon},
+    flood::flood,
+    midpoint::{circle_quadrant, ellipse_quadrant, fill_quadrants, plot_quadrants},
+    stroke::split_dashes,
+};
 use crate::graphics::{
-    matrix::Matrix,
+    lighting::{Lighting, Material, Shading},
+    matrix::{Matrix, Surface},
     utils::{mapper, polar_to_xy},
     vector::Vec3,
     RGB,
 };
 
-// mod turtle;
-// turtle will cause problems with
+pub mod culling;
+pub mod fill;
+pub mod flood;
+pub mod midpoint;
+pub mod stroke;
+pub mod turtle;
+
+pub use culling::{Culling, Face, Winding};
+pub use fill::FillRule;
+pub use flood::{Connectivity, FloodMode};
+pub use stroke::{LineCap, Stroke};
+
+/// How the triangles of a polygon matrix are drawn
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DrawMode {
+    /// Only the three edges of each triangle
+    Wireframe,
+    /// Solid triangles, filled with scanlines
+    Filled,
+}
+
+/// Algorithm used to rasterize lines
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineMode {
+    /// Pixel exact lines with Bresenham's algorithm
+    Bresenham,
+    /// Anti-aliased lines with Xiaolin Wu's algorithm, blended with what's already drawn
+    AntiAliased,
+}
 
 pub trait Canvas {
     /// Plot a point on the screen at (`x`, `y`), must be impl'ed
     fn plot(&mut self, x: i32, y: i32);
 
+    /// Plot a point at (`x`, `y`) only if `z` is nearer than what has been drawn there.
+    /// Larger z is nearer to the viewer.
+    ///
+    /// Canvases without a depth buffer ignore `z` and plot unconditionally.
+    fn plot_z(&mut self, x: i32, y: i32, _z: f64) {
+        self.plot(x, y);
+    }
+
+    /// Samples along each axis of a pixel, more than 1 for supersampled canvases.
+    ///
+    /// Lines and fills are rasterized on a grid this many times finer than the pixels and
+    /// plotted with `plot_sample`, but they are still drawn with coordinates in pixels.
+    fn samples(&self) -> u32 {
+        1
+    }
+
+    /// Plot sample (`x`, `y`) of the grid lines and fills are rasterized on,
+    /// depth tested like `plot_z` if `z` is given.
+    ///
+    /// Samples are pixels unless the canvas is supersampled.
+    fn plot_sample(&mut self, x: i32, y: i32, z: Option<f64>) {
+        match z {
+            Some(z) => self.plot_z(x, y, z),
+            None => self.plot(x, y),
+        }
+    }
+
+    /// Color of sample (`x`, `y`), or None if it's not on the canvas
+    fn get_sample(&self, x: i32, y: i32) -> Option<RGB> {
+        self.get_pixel(x, y)
+    }
+
+    /// Plot sample (`x`, `y`) that is only partially covered by what's being drawn,
+    /// blending the fg color with the current sample by `coverage` in [0, 1].
+    ///
+    /// Depth tested like `plot_z` if `z` is given.
+    fn plot_coverage(&mut self, x: i32, y: i32, z: Option<f64>, coverage: f64) {
+        let under = match self.get_sample(x, y) {
+            Some(color) if coverage > 0. => color,
+            _ => return,
+        };
+        let fg_color = self.get_fg_color();
+        self.set_fg_color(under.blend(fg_color, coverage.min(1.)));
+        self.plot_sample(x, y, z);
+        self.set_fg_color(fg_color);
+    }
+
+    /// Color of the pixel at (`x`, `y`), or None if it's not on the canvas
+    fn get_pixel(&self, x: i32, y: i32) -> Option<RGB>;
+
     // fn index(&self, x: i32, y: i32) -> Option<usize>;
     fn set_fg_color(&mut self, color: RGB);
     fn set_bg_color(&mut self, color: RGB);
     fn get_fg_color(&self) -> RGB;
     fn get_bg_color(&self) -> RGB;
+    /// Stroke used by `draw_line` and `render_edge_matrix`
+    fn set_stroke(&mut self, stroke: Stroke);
+    fn get_stroke(&self) -> &Stroke;
     fn width(&self) -> u32;
     fn height(&self) -> u32;
+    /// Max value of a color channel
+    fn depth(&self) -> u16;
 
-    //----------------------------------------- default methods for drawing lines
+    /// Algorithm used by `draw_line` and `draw_line_z`
+    fn line_mode(&self) -> LineMode {
+        LineMode::Bresenham
+    }
 
-    /// Draw a line from (x0, y0) to (x1, y1)
-    /// #### impl note:
-    ///    Always add 2A or 2B when updating D. Half of that value will distort line
-    fn draw_line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) {
-        // swap variables if needed, since we are always going from left to right
-        let (x0, y0, x1, y1) = if x0 > x1 {
-            (x1, y1, x0, y0)
-        } else {
-            (x0, y0, x1, y1)
-        };
+    /// Which triangles `render_polygon_matrix` and friends draw
+    fn culling(&self) -> Culling {
+        Culling::default()
+    }
 
-        // force conversion into ints for processing & plotting
-        let (x0, y0, x1, y1) = (
-            x0.round() as i32,
-            y0.round() as i32,
-            x1.round() as i32,
-            y1.round() as i32,
-        );
+    /// Whether drawing wraps around in (x, y). Lines are not clipped along a wrapping axis.
+    fn wrap(&self) -> (bool, bool) {
+        (false, false)
+    }
 
-        // calculate  values and then truncate
-        let (dy, ndx) = (y1 - y0, -(x1 - x0));
+    /// Region (xmin, xmax, ymin, ymax) that lines are clipped to before rasterizing.
+    ///
+    /// Bounds are half a pixel outside the canvas so that rounding keeps the edge pixels.
+    fn clip_bounds(&self) -> (f64, f64, f64, f64) {
+        let (x_wrap, y_wrap) = self.wrap();
+        let (w, h) = (self.width() as f64, self.height() as f64);
+        let (xmin, xmax) = if x_wrap {
+            (f64::NEG_INFINITY, f64::INFINITY)
+        } else {
+            (-0.5, w - 0.5)
+        };
+        let (ymin, ymax) = if y_wrap {
+            (f64::NEG_INFINITY, f64::INFINITY)
+        } else {
+            (-0.5, h - 0.5)
+        };
+        (xmin, xmax, ymin, ymax)
+    }
 
-        // deal with special s:
-        if ndx == 0 {
-            // vertical line
-            let (y0, y1) = if y0 < y1 { (y0, y1) } else { (y1, y0) };
+    /// Region (xmin, xmax, ymin, ymax) of samples (see `samples`) that filling needs to cover.
+    ///
+    /// Samples outside of it are skipped when filling triangles. Defaults to everything,
+    /// since samples off the canvas may still wrap around onto it.
+    fn fill_bounds(&self) -> (i32, i32, i32, i32) {
+        (i32::MIN, i32::MAX, i32::MIN, i32::MAX)
+    }
 
-            for y in y0..=y1 {
-                self.plot(x0, y);
-            }
+    //----------------------------------------- default methods for drawing lines
 
-            return ();
+    /// Draw a line from (x0, y0) to (x1, y1) with the current stroke
+    fn draw_line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) {
+        if self.get_stroke().is_hairline() {
+            self.draw_hairline(x0, y0, x1, y1);
+        } else {
+            let stroke = self.get_stroke().clone();
+            self.stroke_line(x0, y0, x1, y1, &stroke, stroke.dash_offset);
         }
+    }
 
-        if dy == 0 {
-            // horizontal line
-            // x vals are already in the right order, so we don't flip
-            for x in x0..=x1 {
-                self.plot(x, y0);
+    /// Draw a solid one pixel wide line from (x0, y0) to (x1, y1)
+    ///
+    /// The line is clipped to `clip_bounds` first, so only visible pixels are walked.
+    /// On supersampled canvases it is filled as a one pixel wide rectangle instead.
+    fn draw_hairline(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) {
+        let s = self.samples() as f64;
+        if s > 1. {
+            let (p0, p1) = ((x0, y0, 0.), (x1, y1, 0.));
+            fill_line_samples(self, p0, p1, s, LineCap::Square, false);
+            return;
+        }
+        if let Some((t0, t1)) = clip_line(x0, y0, x1, y1, self.clip_bounds()) {
+            let (dx, dy) = (x1 - x0, y1 - y0);
+            let (x0, y0, x1, y1) = (x0 + dx * t0, y0 + dy * t0, x0 + dx * t1, y0 + dy * t1);
+            match self.line_mode() {
+                LineMode::Bresenham => {
+                    bresenham(x0, y0, x1, y1, |x, y| self.plot_sample(x, y, None))
+                }
+                LineMode::AntiAliased => xiaolin_wu(x0, y0, x1, y1, |x, y, _t, coverage| {
+                    self.plot_coverage(x, y, None, coverage)
+                }),
             }
-            return ();
         }
+    }
 
-        // find A and B
-        // let m  = -dely as f64 / ndelx as f64;
+    /// Draw a line from (x0, y0) to (x1, y1) with `stroke`, starting `phase` pixels into
+    /// its dash pattern.
+    ///
+    /// Returns the phase at the end of the line, so connected lines can continue the pattern
+    fn stroke_line(
+        &mut self,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+        stroke: &Stroke,
+        phase: f64,
+    ) -> f64 {
+        let len = (x1 - x0).hypot(y1 - y0);
+        if !stroke.is_dashed() || len == 0. {
+            self.draw_thick_line(x0, y0, x1, y1, stroke.width, stroke.cap);
+            return phase;
+        }
 
-        let (x, mut y) = (x0, y0);
+        let (ux, uy) = ((x1 - x0) / len, (y1 - y0) / len);
+        split_dashes(len, stroke.dash(), phase, |start, end| {
+            self.draw_thick_line(
+                x0 + ux * start,
+                y0 + uy * start,
+                x0 + ux * end,
+                y0 + uy * end,
+                stroke.width,
+                stroke.cap,
+            )
+        })
+    }
 
-        if (y1 - y0).abs() < (x1 - x0).abs() {
-            // octant 1 and 8
-            let mut d = 2 * dy + ndx;
-            let (y_inc, dy) = if dy > 0 {
-                // octant 1
-                (1, dy)
-            } else {
-                // octant 8
-                // dy is (-) in octant 8, so flip it to balance out with ndx
-                (-1, -dy)
-            };
+    /// Draw a solid line from (x0, y0) to (x1, y1) `width` pixels wide, with `cap` at both ends
+    ///
+    /// Lines at most one pixel wide are drawn with `draw_hairline`
+    fn draw_thick_line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, width: f64, cap: LineCap) {
+        if width <= 1. {
+            self.draw_hairline(x0, y0, x1, y1);
+            return;
+        }
+        let s = self.samples() as f64;
+        fill_line_samples(self, (x0, y0, 0.), (x1, y1, 0.), width * s, cap, false);
+    }
 
-            for x in x0..=x1 {
-                self.plot(x, y);
-                if d > 0 {
-                    y += y_inc;
-                    d += 2 * ndx;
-                }
-                d += 2 * dy;
-            }
-        } else {
-            // octant 2 and 7
-            // flipping x and y should work out
+    /// Draw a line from (x0, y0, z0) to (x1, y1, z1), depth testing every pixel with `plot_z`
+    ///
+    /// z is interpolated linearly along the major axis of the line.
+    /// The line is clipped to `clip_bounds` first, so only visible pixels are walked.
+    /// On supersampled canvases it is filled as a one pixel wide rectangle instead.
+    fn draw_line_z(&mut self, x0: f64, y0: f64, z0: f64, x1: f64, y1: f64, z1: f64) {
+        let s = self.samples() as f64;
+        if s > 1. {
+            let (p0, p1) = ((x0, y0, z0), (x1, y1, z1));
+            fill_line_samples(self, p0, p1, s, LineCap::Square, true);
+            return;
+        }
+        let (t0, t1) = match clip_line(x0, y0, x1, y1, self.clip_bounds()) {
+            Some(t) => t,
+            None => return,
+        };
+        let (dx, dy, dz) = (x1 - x0, y1 - y0, z1 - z0);
+        let (x0, y0, z0, x1, y1, z1) = (
+            x0 + dx * t0,
+            y0 + dy * t0,
+            z0 + dz * t0,
+            x0 + dx * t1,
+            y0 + dy * t1,
+            z0 + dz * t1,
+        );
 
-            let mut d = 2 * -ndx - dy;
+        if self.line_mode() == LineMode::AntiAliased {
+            xiaolin_wu(x0, y0, x1, y1, |x, y, t, coverage| {
+                self.plot_coverage(x, y, Some(z0 + (z1 - z0) * t), coverage)
+            });
+            return;
+        }
 
-            let (x_inc, mut x, ystart, yend, dy) = if dy > 0 {
-                // octant 2
-                (1, x, y0, y1, dy)
+        let (dx, dy) = (x1.round() - x0.round(), y1.round() - y0.round());
+        let (xs, ys) = (x0.round(), y0.round());
+        bresenham(x0, y0, x1, y1, |x, y| {
+            let t = if dx == 0. && dy == 0. {
+                0.
+            } else if dx.abs() >= dy.abs() {
+                (x as f64 - xs) / dx
             } else {
-                // octant 7
-                // swap -x and y to reflect over y=-x into octant 8
-                (-1, x - ndx, y1, y0, -dy)
+                (y as f64 - ys) / dy
             };
-
-            for y in ystart..=yend {
-                self.plot(x, y);
-                if d > 0 {
-                    x += x_inc;
-                    d -= 2 * dy;
-                }
-                d -= 2 * ndx;
-            }
-        }
+            self.plot_sample(x, y, Some(z0 + (z1 - z0) * t));
+        });
     }
 
     /// Draw a line from (x0, y0) with a certain magnitude and angle
@@ -129,12 +292,44 @@ pub trait Canvas {
         return (x1, y1);
     }
 
+    //----------------------------------------- circles and ellipses
+
+    /// Draw a one pixel wide circle with center (xc, yc) and radius `r`
+    /// with the midpoint circle algorithm
+    fn draw_circle(&mut self, xc: i32, yc: i32, r: u32) {
+        plot_quadrants(self, xc, yc, &circle_quadrant(r));
+    }
+
+    /// Fill the circle with center (xc, yc) and radius `r`, up to and including
+    /// the pixels of `draw_circle`
+    fn fill_circle(&mut self, xc: i32, yc: i32, r: u32) {
+        fill_quadrants(self, xc, yc, &circle_quadrant(r));
+    }
+
+    /// Draw a one pixel wide ellipse with center (xc, yc) and radii `rx` along x and `ry`
+    /// along y with the midpoint ellipse algorithm
+    fn draw_ellipse(&mut self, xc: i32, yc: i32, rx: u32, ry: u32) {
+        plot_quadrants(self, xc, yc, &ellipse_quadrant(rx, ry));
+    }
+
+    /// Fill the ellipse with center (xc, yc) and radii `rx` and `ry`, up to and including
+    /// the pixels of `draw_ellipse`
+    fn fill_ellipse(&mut self, xc: i32, yc: i32, rx: u32, ry: u32) {
+        fill_quadrants(self, xc, yc, &ellipse_quadrant(rx, ry));
+    }
+
     //----------------------------------------- render edge matrix on screen
 
-    /// Draws an edge matrix
+    /// Draws an edge matrix with the current stroke
+    ///
+    /// Dash patterns continue across connected edges, so curves are dashed as a whole.
     ///
     /// Number of edges must be a multiple of 2
     fn render_edge_matrix(&mut self, m: &Matrix) {
+        let stroke = self.get_stroke().clone();
+        let mut phase = stroke.dash_offset;
+        let mut last_end = None;
+
         let mut iter = m.iter_by_row();
         while let Some(point) = iter.next() {
             let (x0, y0, _z0) = (point[0], point[1], point[2]);
@@ -143,7 +338,15 @@ pub trait Canvas {
                 None => panic!("Number of edges must be a multiple of 2"),
             };
 
-            self.draw_line(x0, y0, x1, y1);
+            if stroke.is_hairline() {
+                self.draw_hairline(x0, y0, x1, y1);
+            } else {
+                if last_end != Some((x0, y0)) {
+                    phase = stroke.dash_offset;
+                }
+                phase = self.stroke_line(x0, y0, x1, y1, &stroke, phase);
+                last_end = Some((x1, y1));
+            }
         }
     }
 
@@ -159,45 +362,707 @@ pub trait Canvas {
             };
 
             self.draw_line(
-                map_width(-x0),
+                map_width(x0),
                 map_height(y0),
-                map_width(-x1),
+                map_width(x1),
                 map_height(y1),
             );
         }
     }
 
-    /// Renders polygon matrix `m` onto screen.
+    //----------------------------------------- filling
+
+    /// Fill the triangle with vertices `p0`, `p1`, `p2` using scanlines
+    ///
+    /// z is interpolated along the edges and across each row for depth testing.
+    fn fill_triangle(&mut self, p0: (f64, f64, f64), p1: (f64, f64, f64), p2: (f64, f64, f64)) {
+        let s = self.samples() as f64;
+        let pts = [p0, p1, p2].map(|(x, y, z)| (to_samples(s, x), to_samples(s, y), z));
+        let (none, bounds) = (Vec3(0., 0., 0.), self.fill_bounds());
+        scan_triangle_in(pts, [none; 3], bounds, |x, y, z, _| {
+            self.plot_sample(x, y, Some(z))
+        });
+    }
+
+    /// Fill the polygon made of closed `rings` of (x, y) points with the fg color.
+    ///
+    /// The rings may cross themselves and each other; `rule` decides which parts are inside,
+    /// e.g. a second ring inside the first one is a hole with `EvenOdd`, but only a hole with
+    /// `NonZero` when it goes around the other way.
+    fn fill_polygon(&mut self, rings: &[Vec<(f64, f64)>], rule: FillRule) {
+        let rings = rings_in_samples(rings, self.samples() as f64);
+        scan_polygon(&rings, rule, self.fill_bounds(), |x, y| {
+            self.plot_sample(x, y, None)
+        });
+    }
+
+    /// Same as `fill_polygon`, but each pixel (x, y) is filled with `fill(x, y)`
+    fn fill_polygon_with_fn(
+        &mut self,
+        rings: &[Vec<(f64, f64)>],
+        rule: FillRule,
+        fill: &dyn Fn(f64, f64) -> RGB,
+    ) {
+        let fg_color = self.get_fg_color();
+        let s = self.samples() as f64;
+        let rings = rings_in_samples(rings, s);
+        scan_polygon(&rings, rule, self.fill_bounds(), |x, y| {
+            self.set_fg_color(fill(from_samples(s, x as f64), from_samples(s, y as f64)));
+            self.plot_sample(x, y, None);
+        });
+        self.set_fg_color(fg_color);
+    }
+
+    /// Fill the paths of edge matrix `m` (see `Matrix::paths`) as one polygon.
+    /// Paths that don't end where they start are closed with a straight line.
+    fn fill_edge_matrix(&mut self, m: &Matrix, rule: FillRule) {
+        self.fill_polygon(&m.paths(), rule);
+    }
+
+    /// Flood fill with the fg color from (x, y), spreading over the pixels picked by `mode`
+    /// to their neighbors by `connectivity`.
+    ///
+    /// Pixels are read back with `get_pixel`, so canvases without pixels fill nothing.
+    fn flood_fill(&mut self, x: i32, y: i32, mode: FloodMode, connectivity: Connectivity) {
+        flood(self, x, y, mode, connectivity, |canvas, x, y| {
+            canvas.plot(x, y)
+        });
+    }
+
+    /// Same as `flood_fill`, but each pixel (x, y) is filled with `fill(x, y)`
+    fn flood_fill_with_fn(
+        &mut self,
+        x: i32,
+        y: i32,
+        mode: FloodMode,
+        connectivity: Connectivity,
+        fill: &dyn Fn(f64, f64) -> RGB,
+    ) {
+        let fg_color = self.get_fg_color();
+        flood(self, x, y, mode, connectivity, |canvas, x, y| {
+            canvas.set_fg_color(fill(x as f64, y as f64));
+            canvas.plot(x, y);
+        });
+        self.set_fg_color(fg_color);
+    }
+
+    //----------------------------------------- render polygon matrix on screen
+
+    /// Renders polygon matrix `m` onto screen as a wireframe.
     ///
     /// Removes hidden surface with back-face culling
     fn render_polygon_matrix(&mut self, m: &Matrix) {
-        // view vector for now: v = <0, 0, 1>, not needed for computation
+        self.render_polygon_matrix_with(m, DrawMode::Wireframe);
+    }
 
-        let mut iter = m.iter_by_row();
-        while let Some(point) = iter.next() {
-            let (x0, y0, z0) = (point[0], point[1], point[2]);
-            let (x1, y1, z1) = match iter.next() {
-                Some(p1) => (p1[0], p1[1], p1[2]),
-                None => panic!("Number of points must be a multiple of 2 for edge matrix"),
-            };
-            let (x2, y2, z2) = match iter.next() {
-                Some(p2) => (p2[0], p2[1], p2[2]),
-                None => panic!("Number of points must be a multiple of 3 for polygon matrix"),
-            };
+    /// Renders polygon matrix `m` onto screen, drawing each triangle according to `mode`.
+    ///
+    /// Triangles with a surface set on `m` are drawn in its color, the rest in the foreground color.
+    ///
+    /// Removes hidden surface with face culling (see `culling`) and depth testing
+    fn render_polygon_matrix_with(&mut self, m: &Matrix, mode: DrawMode) {
+        render_triangles(self, m, 0..triangle_count(m), mode);
+    }
+
+    /// Renders polygon matrix `m` onto screen as filled triangles, lit with `lighting`.
+    ///
+    /// `shading` picks how the color varies across each triangle:
+    /// - `Flat`: a single color from the surface normal at the center of the triangle
+    /// - `Gouraud`: colors are computed at the vertices and interpolated across the triangle
+    /// - `Phong`: vertex normals are interpolated and lighting is computed at every pixel
+    ///
+    /// Triangles with a material set on `m` use it instead of `material`, and triangles with a
+    /// color use `material` tinted by that color.
+    ///
+    /// When back faces are drawn, they are lit as seen from behind, with their normals flipped.
+    ///
+    /// Shadows cast by `lighting` are tested at every pixel, except with `Gouraud` shading where
+    /// they are only sampled at the vertices like the rest of the lighting.
+    ///
+    /// Removes hidden surface with face culling (see `culling`) and depth testing
+    fn render_polygon_matrix_lit(
+        &mut self,
+        m: &Matrix,
+        lighting: &Lighting,
+        material: &Material,
+        shading: Shading,
+    ) {
+        let normals = match shading {
+            Shading::Flat => vec![],
+            Shading::Gouraud | Shading::Phong => m.vertex_normals(),
+        };
+        let tris = 0..triangle_count(m);
+        render_triangles_lit(self, m, tris, &normals, lighting, material, shading);
+    }
+}
 
-            let v0 = Vec3(x0, y0, z0);
-            let v1 = Vec3(x1, y1, z1);
-            let v2 = Vec3(x2, y2, z2);
+/// Number of triangles in polygon matrix `m`
+pub(crate) fn triangle_count(m: &Matrix) -> usize {
+    let rows = m.iter_by_row().len();
+    assert_eq!(0, rows % 3, "Number of points must be a multiple of 3");
+    rows / 3
+}
+
+/// Vertices of triangle `i` of polygon matrix `m`
+fn triangle(m: &Matrix, i: usize) -> (Vec3, Vec3, Vec3) {
+    let vertex = |row: usize| {
+        let mut p = m.row_iter(row);
+        let mut next = || *p.next().unwrap();
+        Vec3(next(), next(), next())
+    };
+    (vertex(i * 3), vertex(i * 3 + 1), vertex(i * 3 + 2))
+}
 
-            let vn = (v1 - v0).cross(v2 - v0);
+/// Draws triangles `tris` of polygon matrix `m` onto `canvas`, for `render_polygon_matrix_with`
+pub(crate) fn render_triangles<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    m: &Matrix,
+    tris: impl IntoIterator<Item = usize>,
+    mode: DrawMode,
+) {
+    let fg_color = canvas.get_fg_color();
+    let depth = canvas.depth();
+    let culling = canvas.culling();
 
-            if vn.2 > 0. {
-                self.draw_line(x0, y0, x1, y1);
-                self.draw_line(x1, y1, x2, y2);
-                self.draw_line(x2, y2, x0, y0);
+    for i in tris {
+        let (v0, v1, v2) = triangle(m, i);
+        if culling.facing(v0, v1, v2).is_some() {
+            canvas.set_fg_color(match m.surface_at(i * 3) {
+                Some(Surface::Color(color)) => color,
+                Some(Surface::Material(material)) => material.color(depth),
+                None => fg_color,
+            });
+            match mode {
+                DrawMode::Wireframe => {
+                    canvas.draw_line_z(v0.0, v0.1, v0.2, v1.0, v1.1, v1.2);
+                    canvas.draw_line_z(v1.0, v1.1, v1.2, v2.0, v2.1, v2.2);
+                    canvas.draw_line_z(v2.0, v2.1, v2.2, v0.0, v0.1, v0.2);
+                }
+                DrawMode::Filled => {
+                    canvas.fill_triangle(v0.into(), v1.into(), v2.into());
+                }
             }
         }
     }
+
+    canvas.set_fg_color(fg_color);
+}
+
+/// Draws triangles `tris` of polygon matrix `m` onto `canvas`, for `render_polygon_matrix_lit`.
+///
+/// `normals` are the vertex normals of all of `m`, needed unless shading is flat.
+pub(crate) fn render_triangles_lit<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    m: &Matrix,
+    tris: impl IntoIterator<Item = usize>,
+    normals: &[Vec3],
+    lighting: &Lighting,
+    material: &Material,
+    shading: Shading,
+) {
+    let fg_color = canvas.get_fg_color();
+    let depth = canvas.depth();
+    let culling = canvas.culling();
+    let to_vec3 = |c: RGB| Vec3(c.red as f64, c.green as f64, c.blue as f64);
+    let to_rgb = |v: Vec3| RGB::new(v.0.round() as u16, v.1.round() as u16, v.2.round() as u16);
+    let bounds = canvas.fill_bounds();
+    let s = canvas.samples() as f64;
+    // lighting is computed at the pixel position of each sample
+    let at = |x: i32, y: i32, z: f64| Vec3(from_samples(s, x as f64), from_samples(s, y as f64), z);
+
+    for i in tris {
+        let (v0, v1, v2) = triangle(m, i);
+        // vertex normals follow counter-clockwise winding, flip them to the side being seen
+        let flip = match (culling.facing(v0, v1, v2), culling.winding) {
+            (None, _) => continue,
+            (Some(Face::Front), Winding::CounterClockwise)
+            | (Some(Face::Back), Winding::Clockwise) => 1.,
+            _ => -1.,
+        };
+        let vn = (v1 - v0).cross(v2 - v0) * flip;
+
+        let material = &match m.surface_at(i * 3) {
+            Some(Surface::Color(color)) => material.tinted(color, depth),
+            Some(Surface::Material(material)) => material,
+            None => *material,
+        };
+        let pts = [v0, v1, v2].map(|v| (to_samples(s, v.0), to_samples(s, v.1), v.2));
+        match shading {
+            Shading::Flat if lighting.has_shadows() => {
+                let center = (v0 + v1 + v2) * (1. / 3.);
+                scan_triangle_in(pts, [Vec3(0., 0., 0.); 3], bounds, |x, y, z, _| {
+                    let color = lighting.shade_shadowed(center, vn, material, depth, at(x, y, z));
+                    canvas.set_fg_color(color);
+                    canvas.plot_sample(x, y, Some(z));
+                });
+            }
+            Shading::Flat => {
+                let center = (v0 + v1 + v2) * (1. / 3.);
+                canvas.set_fg_color(lighting.shade(center, vn, material, depth));
+                canvas.fill_triangle(v0.into(), v1.into(), v2.into());
+            }
+            Shading::Gouraud => {
+                let colors = [
+                    to_vec3(lighting.shade(v0, normals[i * 3] * flip, material, depth)),
+                    to_vec3(lighting.shade(v1, normals[i * 3 + 1] * flip, material, depth)),
+                    to_vec3(lighting.shade(v2, normals[i * 3 + 2] * flip, material, depth)),
+                ];
+                scan_triangle_in(pts, colors, bounds, |x, y, z, color| {
+                    canvas.set_fg_color(to_rgb(color));
+                    canvas.plot_sample(x, y, Some(z));
+                });
+            }
+            Shading::Phong => {
+                let vertex_normals = [
+                    normals[i * 3] * flip,
+                    normals[i * 3 + 1] * flip,
+                    normals[i * 3 + 2] * flip,
+                ];
+                scan_triangle_in(pts, vertex_normals, bounds, |x, y, z, normal| {
+                    canvas.set_fg_color(lighting.shade(at(x, y, z), normal, material, depth));
+                    canvas.plot_sample(x, y, Some(z));
+                });
+            }
+        }
+    }
+
+    canvas.set_fg_color(fg_color);
+}
+
+/// Position on the sample grid of `x` in pixels, with `s` samples per pixel.
+///
+/// The samples of pixel x are s * x to s * x + s - 1, centered around it.
+pub(crate) fn to_samples(s: f64, x: f64) -> f64 {
+    if s == 1. {
+        x
+    } else {
+        (x + 0.5) * s - 0.5
+    }
+}
+
+/// Position in pixels of `x` on the sample grid, the inverse of `to_samples`
+pub(crate) fn from_samples(s: f64, x: f64) -> f64 {
+    if s == 1. {
+        x
+    } else {
+        (x + 0.5) / s - 0.5
+    }
+}
+
+/// `rings` of points in pixels moved onto the sample grid
+fn rings_in_samples(rings: &[Vec<(f64, f64)>], s: f64) -> Vec<Vec<(f64, f64)>> {
+    rings
+        .iter()
+        .map(|ring| {
+            ring.iter()
+                .map(|&(x, y)| (to_samples(s, x), to_samples(s, y)))
+                .collect()
+        })
+        .collect()
+}
+
+/// Color of pixel (x, y) of a supersampled canvas, the average of its samples
+pub(crate) fn average_samples<C: Canvas + ?Sized>(canvas: &C, x: i32, y: i32) -> Option<RGB> {
+    let s = canvas.samples() as i32;
+    let (mut r, mut g, mut b) = (0u64, 0u64, 0u64);
+    for sy in 0..s {
+        for sx in 0..s {
+            let c = canvas.get_sample(x.saturating_mul(s) + sx, y.saturating_mul(s) + sy)?;
+            r += c.red as u64;
+            g += c.green as u64;
+            b += c.blue as u64;
+        }
+    }
+    let n = (s * s) as f64;
+    let avg = |c: u64| (c as f64 / n).round() as u16;
+    Some(RGB::new(avg(r), avg(g), avg(b)))
+}
+
+/// Fill the line from (x0, y0, z0) to (x1, y1, z1), in pixels, as a rectangle `width`
+/// samples wide on the sample grid, with `cap` at both ends.
+///
+/// z is interpolated along the line, and every sample is depth tested if `depth_test`.
+/// Only the part of the line that can reach `clip_bounds` is filled.
+pub(crate) fn fill_line_samples<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    (x0, y0, z0): (f64, f64, f64),
+    (x1, y1, z1): (f64, f64, f64),
+    width: f64,
+    cap: LineCap,
+    depth_test: bool,
+) {
+    let s = canvas.samples() as f64;
+    let (x0, y0, x1, y1) = (
+        to_samples(s, x0),
+        to_samples(s, y0),
+        to_samples(s, x1),
+        to_samples(s, y1),
+    );
+    let r = width / 2.;
+    let len = (x1 - x0).hypot(y1 - y0);
+    let (ux, uy) = if len == 0. {
+        (1., 0.)
+    } else {
+        ((x1 - x0) / len, (y1 - y0) / len)
+    };
+    let (x0, y0, x1, y1) = if cap == LineCap::Square {
+        (x0 - ux * r, y0 - uy * r, x1 + ux * r, y1 + uy * r)
+    } else {
+        (x0, y0, x1, y1)
+    };
+
+    // only fill the part that can reach the canvas
+    let (xmin, xmax, ymin, ymax) = canvas.clip_bounds();
+    let (xmin, xmax) = (to_samples(s, xmin), to_samples(s, xmax));
+    let (ymin, ymax) = (to_samples(s, ymin), to_samples(s, ymax));
+    let bounds = (xmin - r, xmax + r, ymin - r, ymax + r);
+    let (x0, y0, z0, x1, y1, z1) = match clip_line(x0, y0, x1, y1, bounds) {
+        Some((t0, t1)) => {
+            let (dx, dy, dz) = (x1 - x0, y1 - y0, z1 - z0);
+            (
+                x0 + dx * t0,
+                y0 + dy * t0,
+                z0 + dz * t0,
+                x0 + dx * t1,
+                y0 + dy * t1,
+                z0 + dz * t1,
+            )
+        }
+        None => return,
+    };
+    let z = |z: f64| if depth_test { Some(z) } else { None };
+
+    // the body of the line is a rectangle, filled as two triangles
+    if len > 0. || cap == LineCap::Square {
+        let (nx, ny) = (-uy * r, ux * r);
+        let corners = [
+            (x0 + nx, y0 + ny, z0),
+            (x1 + nx, y1 + ny, z1),
+            (x1 - nx, y1 - ny, z1),
+            (x0 - nx, y0 - ny, z0),
+        ];
+        let none = [Vec3(0., 0., 0.); 3];
+        let bounds = canvas.fill_bounds();
+        for tri in [[0, 1, 2], [0, 2, 3]].iter() {
+            let pts = [corners[tri[0]], corners[tri[1]], corners[tri[2]]];
+            scan_triangle_in(pts, none, bounds, |x, y, zi, _| {
+                canvas.plot_sample(x, y, z(zi))
+            });
+        }
+    }
+
+    if cap == LineCap::Round {
+        for &(cx, cy, cz) in [(x0, y0, z0), (x1, y1, z1)].iter() {
+            for y in (cy - r).ceil() as i32..=(cy + r).floor() as i32 {
+                let half = (r * r - (y as f64 - cy).powi(2)).sqrt();
+                for x in (cx - half).round() as i32..=(cx + half).round() as i32 {
+                    canvas.plot_sample(x, y, z(cz));
+                }
+            }
+        }
+    }
+}
+
+/// Walk the pixels of the line from (x0, y0) to (x1, y1) with Xiaolin Wu's algorithm,
+/// calling `plot` with (x, y, t, coverage) on each of them, where t in [0, 1] is how far along
+/// the line the pixel is and coverage in [0, 1] is how much of the pixel the line covers.
+pub(crate) fn xiaolin_wu(
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+    mut plot: impl FnMut(i32, i32, f64, f64),
+) {
+    let fpart = |v: f64| v - v.floor();
+    let rfpart = |v: f64| 1. - fpart(v);
+
+    // walk along x, swapping x and y for steep lines
+    let steep = (y1 - y0).abs() > (x1 - x0).abs();
+    let (x0, y0, x1, y1) = if steep {
+        (y0, x0, y1, x1)
+    } else {
+        (x0, y0, x1, y1)
+    };
+    // always go from left to right
+    let reversed = x0 > x1;
+    let (x0, y0, x1, y1) = if reversed {
+        (x1, y1, x0, y0)
+    } else {
+        (x0, y0, x1, y1)
+    };
+
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let gradient = if dx == 0. { 1. } else { dy / dx };
+
+    let mut plot = |x: f64, y: f64, coverage: f64| {
+        let t = if dx == 0. {
+            0.
+        } else {
+            ((x - x0) / dx).clamp(0., 1.)
+        };
+        let t = if reversed { 1. - t } else { t };
+        let (x, y) = (x as i32, y as i32);
+        if steep {
+            plot(y, x, t, coverage);
+        } else {
+            plot(x, y, t, coverage);
+        }
+    };
+
+    // first endpoint
+    let xend = (x0 + 0.5).floor();
+    let yend = y0 + gradient * (xend - x0);
+    let xgap = rfpart(x0 + 0.5);
+    let (xpxl1, ypxl1) = (xend, yend.floor());
+    plot(xpxl1, ypxl1, rfpart(yend) * xgap);
+    plot(xpxl1, ypxl1 + 1., fpart(yend) * xgap);
+    let mut intery = yend + gradient;
+
+    // second endpoint
+    let xend = (x1 + 0.5).floor();
+    let yend = y1 + gradient * (xend - x1);
+    let xgap = fpart(x1 + 0.5);
+    let (xpxl2, ypxl2) = (xend, yend.floor());
+    plot(xpxl2, ypxl2, rfpart(yend) * xgap);
+    plot(xpxl2, ypxl2 + 1., fpart(yend) * xgap);
+
+    // everything in between
+    let mut x = xpxl1 + 1.;
+    while x < xpxl2 {
+        plot(x, intery.floor(), rfpart(intery));
+        plot(x, intery.floor() + 1., fpart(intery));
+        intery += gradient;
+        x += 1.;
+    }
+}
+
+/// Fill the triangle with vertices `pts` using scanlines, calling `plot` with (x, y, z, attribute)
+/// for every pixel inside.
+///
+/// Vertices are sorted into bottom, middle and top by y. Each row walks the long edge
+/// (bottom -> top) and one of the two short edges (bottom -> middle or middle -> top).
+/// z and the per-vertex `attrs` (e.g. colors or normals) are interpolated along the edges
+/// and then across each row. A pixel is inside when its center is, like with `scan_polygon`.
+pub(crate) fn scan_triangle(
+    pts: [(f64, f64, f64); 3],
+    attrs: [Vec3; 3],
+    plot: impl FnMut(i32, i32, f64, Vec3),
+) {
+    let everything = (i32::MIN, i32::MAX, i32::MIN, i32::MAX);
+    scan_triangle_in(pts, attrs, everything, plot);
+}
+
+/// Same as `scan_triangle`, but only visits pixels within (xmin, xmax, ymin, ymax).
+///
+/// Pixels that are visited get exactly the same values as with `scan_triangle`.
+pub(crate) fn scan_triangle_in(
+    pts: [(f64, f64, f64); 3],
+    attrs: [Vec3; 3],
+    (xmin, xmax, ymin, ymax): (i32, i32, i32, i32),
+    mut plot: impl FnMut(i32, i32, f64, Vec3),
+) {
+    let mut verts = [(pts[0], attrs[0]), (pts[1], attrs[1]), (pts[2], attrs[2])];
+    verts.sort_by(|a, b| (a.0).1.total_cmp(&(b.0).1));
+    let [b, m, t] = verts;
+    let (yb, ym, yt) = ((b.0).1, (m.0).1, (t.0).1);
+
+    // (x, z, attr) on the segment from -> to at height y
+    let lerp = |((xa, ya, za), aa): ((f64, f64, f64), Vec3),
+                ((xz, yz, zz), az): ((f64, f64, f64), Vec3),
+                y: f64| {
+        if yz == ya {
+            (xz, zz, az)
+        } else {
+            let t = (y - ya) / (yz - ya);
+            (xa + (xz - xa) * t, za + (zz - za) * t, aa + (az - aa) * t)
+        }
+    };
+
+    for y in centers_in(yb, yt, (ymin, ymax)) {
+        let yf = y as f64;
+        let long = lerp(b, t, yf);
+        let short = if yf < ym {
+            lerp(b, m, yf)
+        } else {
+            lerp(m, t, yf)
+        };
+        let (left, right) = if long.0 > short.0 {
+            (short, long)
+        } else {
+            (long, short)
+        };
+
+        for x in centers_in(left.0, right.0, (xmin, xmax)) {
+            let s = (x as f64 - left.0) / (right.0 - left.0);
+            plot(
+                x,
+                y,
+                left.1 + (right.1 - left.1) * s,
+                left.2 + (right.2 - left.2) * s,
+            );
+        }
+    }
+}
+
+/// Cohen–Sutherland line clipping against the rectangle `(xmin, xmax, ymin, ymax)`
+///
+/// Returns the parameters (t0, t1) of the visible part of the segment
+/// (x0, y0) + t * (x1 - x0, y1 - y0), or None if it's completely outside.
+pub(crate) fn clip_line(
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+    (xmin, xmax, ymin, ymax): (f64, f64, f64, f64),
+) -> Option<(f64, f64)> {
+    const LEFT: u8 = 1;
+    const RIGHT: u8 = 2;
+    const BOTTOM: u8 = 4;
+    const TOP: u8 = 8;
+
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let outcode = |t: f64| {
+        let (x, y) = (x0 + dx * t, y0 + dy * t);
+        let mut code = 0;
+        if x < xmin {
+            code |= LEFT;
+        } else if x > xmax {
+            code |= RIGHT;
+        }
+        if y < ymin {
+            code |= BOTTOM;
+        } else if y > ymax {
+            code |= TOP;
+        }
+        code
+    };
+
+    let (mut t0, mut t1) = (0., 1.);
+    let (mut code0, mut code1) = (outcode(t0), outcode(t1));
+    loop {
+        if code0 | code1 == 0 {
+            return Some((t0, t1));
+        }
+        if code0 & code1 != 0 {
+            return None;
+        }
+
+        // move the endpoint that's outside onto the boundary it crosses
+        let code = if code0 != 0 { code0 } else { code1 };
+        let t = if code & LEFT != 0 {
+            (xmin - x0) / dx
+        } else if code & RIGHT != 0 {
+            (xmax - x0) / dx
+        } else if code & BOTTOM != 0 {
+            (ymin - y0) / dy
+        } else {
+            (ymax - y0) / dy
+        };
+
+        if code == code0 {
+            t0 = t;
+            // exact boundary points may land a hair outside, snap them in
+            code0 = outcode(t0) & !code;
+        } else {
+            t1 = t;
+            code1 = outcode(t1) & !code;
+        }
+    }
+}
+
+/// Walk the pixels of the line from (x0, y0) to (x1, y1) with Bresenham's algorithm,
+/// calling `plot` on each of them
+/// #### impl note:
+///    Always add 2A or 2B when updating D. Half of that value will distort line
+pub(crate) fn bresenham(x0: f64, y0: f64, x1: f64, y1: f64, mut plot: impl FnMut(i32, i32)) {
+    // swap variables if needed, since we are always going from left to right
+    let (x0, y0, x1, y1) = if x0 > x1 {
+        (x1, y1, x0, y0)
+    } else {
+        (x0, y0, x1, y1)
+    };
+
+    // force conversion into ints for processing & plotting
+    let (x0, y0, x1, y1) = (
+        x0.round() as i32,
+        y0.round() as i32,
+        x1.round() as i32,
+        y1.round() as i32,
+    );
+
+    // calculate  values and then truncate
+    let (dy, ndx) = (y1 - y0, -(x1 - x0));
+
+    // deal with special s:
+    if ndx == 0 {
+        // vertical line
+        let (y0, y1) = if y0 < y1 { (y0, y1) } else { (y1, y0) };
+
+        for y in y0..=y1 {
+            plot(x0, y);
+        }
+
+        return;
+    }
+
+    if dy == 0 {
+        // horizontal line
+        // x vals are already in the right order, so we don't flip
+        for x in x0..=x1 {
+            plot(x, y0);
+        }
+        return;
+    }
+
+    // find A and B
+    // let m  = -dely as f64 / ndelx as f64;
+
+    let (x, mut y) = (x0, y0);
+
+    if (y1 - y0).abs() < (x1 - x0).abs() {
+        // octant 1 and 8
+        let mut d = 2 * dy + ndx;
+        let (y_inc, dy) = if dy > 0 {
+            // octant 1
+            (1, dy)
+        } else {
+            // octant 8
+            // dy is (-) in octant 8, so flip it to balance out with ndx
+            (-1, -dy)
+        };
+
+        for x in x0..=x1 {
+            plot(x, y);
+            if d > 0 {
+                y += y_inc;
+                d += 2 * ndx;
+            }
+            d += 2 * dy;
+        }
+    } else {
+        // octant 2 and 7
+        // flipping x and y should work out
+
+        let mut d = 2 * -ndx - dy;
+
+        let (x_inc, mut x, ystart, yend, dy) = if dy > 0 {
+            // octant 2
+            (1, x, y0, y1, dy)
+        } else {
+            // octant 7
+            // swap -x and y to reflect over y=-x into octant 8
+            (-1, x - ndx, y1, y0, -dy)
+        };
+
+        for y in ystart..=yend {
+            plot(x, y);
+            if d > 0 {
+                x += x_inc;
+                d -= 2 * dy;
+            }
+            d -= 2 * ndx;
+        }
+    }
 }
 
 #[cfg(test)]
@@ -228,4 +1093,309 @@ mod tests {
             "Expect equivalent images by adding lines vs. drawing polygon"
         );
     }
+
+    #[test]
+    fn test_fill_triangle() {
+        let (h, w, d) = (100, 100, 255);
+        let mut img = PPMImg::new(h, w, d);
+
+        let mut m = Matrix::new_polygon_matrix();
+        m.append_polygon((10., 10., 0.), (90., 10., 0.), (50., 90., 0.));
+        img.render_polygon_matrix_with(&m, DrawMode::Filled);
+
+        let color_at = |x, y| img.get_pixel(x, y).unwrap();
+        let (fg, bg) = (img.get_fg_color(), img.get_bg_color());
+
+        // edges and interior are filled, up to the pixels just before the right and top ends
+        for &(x, y) in [(10, 10), (89, 10), (50, 89), (50, 10), (50, 50), (30, 40)].iter() {
+            assert!(color_at(x, y) == fg, "({}, {}) should be filled", x, y);
+        }
+        // points outside the triangle are untouched, and so are the pixels on its right and top
+        // ends, which belong to whatever shares them
+        for &(x, y) in [(5, 5), (95, 50), (10, 80), (50, 95), (90, 10), (50, 90)].iter() {
+            assert!(color_at(x, y) == bg, "({}, {}) should not be filled", x, y);
+        }
+    }
+
+    #[test]
+    fn test_fill_triangle_surfaces() {
+        let mut img = PPMImg::new(100, 100, 255);
+        let red = RGB::new(255, 0, 0);
+        let green = Material::new((0., 0., 0.), (0., 1., 0.), (0., 0., 0.), 1.);
+
+        let mut m = Matrix::new_polygon_matrix();
+        m.set_surface(Surface::Color(red));
+        m.append_polygon((0., 0., 0.), (40., 0., 0.), (0., 40., 0.));
+        m.set_surface(Surface::Material(green));
+        m.append_polygon((50., 0., 0.), (90., 0., 0.), (50., 40., 0.));
+        m.unset_surface();
+        m.append_polygon((0., 50., 0.), (40., 50., 0.), (0., 90., 0.));
+        img.render_polygon_matrix_with(&m, DrawMode::Filled);
+
+        assert_eq!(Some(red), img.get_pixel(10, 10));
+        assert_eq!(Some(RGB::new(0, 255, 0)), img.get_pixel(60, 10));
+        assert_eq!(Some(img.get_fg_color()), img.get_pixel(10, 60));
+    }
+
+    #[test]
+    fn test_fill_triangle_culled() {
+        let (h, w, d) = (100, 100, 255);
+        let blank = PPMImg::new(h, w, d);
+        let mut img = PPMImg::new(h, w, d);
+
+        // clockwise, so it faces away from the viewer
+        let mut m = Matrix::new_polygon_matrix();
+        m.append_polygon((10., 10., 0.), (50., 90., 0.), (90., 10., 0.));
+        img.render_polygon_matrix_with(&m, DrawMode::Filled);
+
+        assert_eq!(blank, img, "Back faces should not be filled");
+    }
+
+    #[test]
+    fn test_culling_settings() {
+        use culling::{CullFace, View};
+
+        // clockwise as seen from +z
+        let mut m = Matrix::new_polygon_matrix();
+        m.append_polygon((10., 10., 0.), (50., 90., 0.), (90., 10., 0.));
+        let drawn = |culling: Culling| {
+            let mut img = PPMImg::new(100, 100, 255);
+            img.culling = culling;
+            img.render_polygon_matrix_with(&m, DrawMode::Filled);
+            img.get_pixel(50, 50) == Some(img.get_fg_color())
+        };
+
+        let front = |cull, winding| Culling::new(cull, winding, View::Vector(Vec3(0., 0., 1.)));
+        assert!(drawn(front(CullFace::Back, Winding::Clockwise)));
+        assert!(!drawn(front(CullFace::Front, Winding::Clockwise)));
+        assert!(drawn(front(CullFace::Front, Winding::CounterClockwise)));
+        assert!(drawn(front(CullFace::None, Winding::CounterClockwise)));
+        // seen from below
+        let eye = View::Eye(Vec3(50., 50., -100.));
+        let from_below = Culling::new(CullFace::Back, Winding::CounterClockwise, eye);
+        assert!(drawn(from_below));
+    }
+
+    #[test]
+    fn test_back_faces_lit_from_behind() {
+        use crate::graphics::lighting::Light;
+        use culling::CullFace;
+
+        let mut lighting = Lighting::new(RGB::gray(0));
+        lighting.add_light(Light::Directional {
+            direction: Vec3(0., 0., -1.),
+            color: RGB::gray(255),
+        });
+        let material = Material::new((0., 0., 0.), (1., 1., 1.), (0., 0., 0.), 1.);
+
+        let mut m = Matrix::new_polygon_matrix();
+        m.append_polygon((10., 10., 0.), (50., 90., 0.), (90., 10., 0.));
+        for &shading in [Shading::Flat, Shading::Gouraud, Shading::Phong].iter() {
+            let mut img = PPMImg::new(100, 100, 255);
+            img.culling.cull = CullFace::None;
+            img.render_polygon_matrix_lit(&m, &lighting, &material, shading);
+            assert_eq!(Some(RGB::gray(255)), img.get_pixel(50, 50), "{:?}", shading);
+        }
+    }
+
+    #[test]
+    fn test_zbuffer_nearest_wins() {
+        let (h, w, d) = (100, 100, 255);
+        let (red, blue) = (RGB::new(255, 0, 0), RGB::new(0, 0, 255));
+
+        let mut near = Matrix::new_polygon_matrix();
+        near.append_polygon((10., 10., 50.), (90., 10., 50.), (50., 90., 50.));
+        let mut far = Matrix::new_polygon_matrix();
+        far.append_polygon((10., 10., -50.), (90., 10., -50.), (50., 90., -50.));
+
+        // draw in both orders, the near triangle should always end up on top
+        let mut near_first = PPMImg::new(h, w, d);
+        near_first.set_fg_color(red);
+        near_first.render_polygon_matrix_with(&near, DrawMode::Filled);
+        near_first.set_fg_color(blue);
+        near_first.render_polygon_matrix_with(&far, DrawMode::Filled);
+
+        let mut far_first = PPMImg::new(h, w, d);
+        far_first.set_fg_color(blue);
+        far_first.render_polygon_matrix_with(&far, DrawMode::Filled);
+        far_first.set_fg_color(red);
+        far_first.render_polygon_matrix_with(&near, DrawMode::Filled);
+
+        assert_eq!(near_first, far_first);
+        assert!(near_first.get_pixel(50, 50).unwrap() == red);
+
+        // clearing resets the depth buffer as well
+        near_first.clear();
+        near_first.set_fg_color(blue);
+        near_first.render_polygon_matrix_with(&far, DrawMode::Filled);
+        assert!(near_first.get_pixel(50, 50).unwrap() == blue);
+    }
+
+    #[test]
+    fn test_zbuffer_intersecting_lines() {
+        let mut img = PPMImg::new(100, 100, 255);
+        let (red, blue) = (RGB::new(255, 0, 0), RGB::new(0, 0, 255));
+
+        // two lines crossing at (50, 50), the red one is nearer there
+        img.set_fg_color(red);
+        img.draw_line_z(0., 50., 10., 100., 50., 10.);
+        img.set_fg_color(blue);
+        img.draw_line_z(50., 0., -100., 50., 100., 100.);
+
+        assert!(img.get_pixel(50, 50).unwrap() == red);
+        assert!(img.get_pixel(50, 90).unwrap() == blue);
+    }
+
+    #[test]
+    fn test_shading_modes_agree_on_flat_surface() {
+        use crate::graphics::lighting::{Light, Lighting, Material, Shading};
+
+        let mut lighting = Lighting::new(RGB::gray(50));
+        lighting.add_light(Light::Directional {
+            direction: Vec3(-1., -1., -1.),
+            color: RGB::new(255, 128, 0),
+        });
+        let material = Material::new((0.2, 0.2, 0.2), (0.6, 0.6, 0.6), (0., 0., 0.), 1.);
+
+        let mut m = Matrix::new_polygon_matrix();
+        m.append_polygon((10., 10., 0.), (90., 10., 0.), (50., 90., 0.));
+
+        let render = |shading| {
+            let mut img = PPMImg::new(100, 100, 255);
+            img.render_polygon_matrix_lit(&m, &lighting, &material, shading);
+            img
+        };
+        let flat = render(Shading::Flat);
+        assert_eq!(flat, render(Shading::Gouraud));
+        assert_eq!(flat, render(Shading::Phong));
+        assert!(flat.get_pixel(50, 50).unwrap() != flat.get_bg_color());
+    }
+
+    #[test]
+    fn test_draw_line_clipped() {
+        let mut clipped = PPMImg::new(100, 100, 255);
+        let mut visible = PPMImg::new(100, 100, 255);
+
+        // a huge line is only as expensive as the visible part
+        clipped.draw_line(-1e12, 50., 1e12, 50.);
+        visible.draw_line(0., 50., 99., 50.);
+        assert_eq!(visible, clipped);
+
+        // lines entirely off screen draw nothing
+        clipped.clear();
+        visible.clear();
+        clipped.draw_line(-1e12, -5., 1e12, -1e6);
+        clipped.draw_line_z(200., 0., 0., 300., 99., 0.);
+        assert_eq!(visible, clipped);
+    }
+
+    #[test]
+    fn test_draw_line_clipped_wraps() {
+        let mut img = PPMImg::new(100, 100, 255);
+        img.x_wrap = true;
+
+        // wraps around in x, but is still clipped in y
+        img.draw_line(50., 50., 150., 50.);
+        img.draw_line(10., -1e12, 10., 1e12);
+        let fg = img.get_fg_color();
+        assert_eq!(fg, img.get_pixel(25, 50).unwrap());
+        assert_eq!(fg, img.get_pixel(10, 0).unwrap());
+        assert_eq!(fg, img.get_pixel(10, 99).unwrap());
+    }
+
+    #[test]
+    fn test_draw_line_antialiased() {
+        let mut aliased = PPMImg::new(100, 100, 255);
+        aliased.draw_line(0., 0., 99., 30.);
+        assert_eq!(LineMode::Bresenham, aliased.line_mode());
+
+        let mut img = PPMImg::new(100, 100, 255);
+        img.line_mode = LineMode::AntiAliased;
+        img.draw_line(0., 0., 99., 30.);
+        assert_ne!(aliased, img);
+
+        // coverage of the two pixels in every column adds up to the full color
+        for x in 1..99 {
+            let total: u16 = (0..100).map(|y| img.get_pixel(x, y).unwrap().red).sum();
+            assert!(
+                (254..=256).contains(&total),
+                "column {} sums to {}",
+                x,
+                total
+            );
+        }
+    }
+
+    #[test]
+    fn test_draw_line_stroke() {
+        let mut img = PPMImg::new(100, 100, 255);
+        img.set_stroke(Stroke::new(5., LineCap::Butt));
+        img.draw_line(10., 50., 90., 50.);
+        let fg = img.get_fg_color();
+
+        // 5 pixels wide, and ends at its endpoints
+        for y in 48..=52 {
+            assert_eq!(Some(fg), img.get_pixel(50, y));
+        }
+        assert_ne!(Some(fg), img.get_pixel(50, 54));
+        assert_ne!(Some(fg), img.get_pixel(7, 50));
+
+        // square caps extend past the endpoints
+        img.clear();
+        img.set_stroke(Stroke::new(5., LineCap::Square));
+        img.draw_line(10., 50., 90., 50.);
+        assert_eq!(Some(fg), img.get_pixel(8, 50));
+        assert_eq!(Some(fg), img.get_pixel(92, 52));
+
+        // round caps add a half disc
+        img.clear();
+        img.set_stroke(Stroke::new(9., LineCap::Round));
+        img.draw_line(50., 10., 50., 90.);
+        assert_eq!(Some(fg), img.get_pixel(50, 94));
+        assert_ne!(Some(fg), img.get_pixel(54, 94));
+    }
+
+    #[test]
+    fn test_draw_line_dashed() {
+        let mut img = PPMImg::new(100, 100, 255);
+        img.set_stroke(Stroke::default().dashed(&[10., 5.], 0.));
+        img.draw_line(0., 50., 99., 50.);
+        let fg = img.get_fg_color();
+
+        let drawn: Vec<bool> = (0..100).map(|x| img.get_pixel(x, 50) == Some(fg)).collect();
+        assert!(drawn[0..=10].iter().all(|&d| d));
+        assert!(drawn[12..15].iter().all(|&d| !d));
+        assert!(drawn[15..=25].iter().all(|&d| d));
+
+        // the pattern continues along a curve made of many short edges
+        let mut curve = PPMImg::new(100, 100, 255);
+        curve.set_stroke(Stroke::default().dashed(&[10., 5.], 0.));
+        let mut m = Matrix::new_edge_matrix();
+        for x in 0..99 {
+            m.append_edge(&[x as f64, 50., 0., x as f64 + 1., 50., 0.]);
+        }
+        curve.render_edge_matrix(&m);
+        assert_eq!(img, curve);
+    }
+
+    #[test]
+    fn test_alpha_compositing() {
+        let mut img = PPMImg::new_with_bg(10, 10, 255, RGB::new(0, 0, 200));
+        img.set_fg_color(RGB::new(255, 0, 0));
+        img.plot(1, 1);
+
+        // half transparent white over the red pixel and over the background
+        img.set_fg_color(RGB::gray(255));
+        img.fg_alpha = 128;
+        img.plot(1, 1);
+        img.plot(2, 2);
+
+        assert_eq!(Some(RGB::new(255, 128, 128)), img.get_pixel(1, 1));
+        assert_eq!(Some(RGB::new(128, 128, 228)), img.get_pixel(2, 2));
+
+        // untouched pixels are flattened against whatever the bg color is
+        assert_eq!(Some(RGB::new(0, 0, 200)), img.get_pixel(5, 5));
+        img.set_bg_color(RGB::gray(10));
+        assert_eq!(Some(RGB::gray(10)), img.get_pixel(5, 5));
+    }
 }
@@ -0,0 +1,151 @@
+//! Stroke style for lines: width, caps and dashes
+
+/// Shape drawn at both ends of a thick line (and of every dash)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    /// Line ends exactly at its endpoints
+    Butt,
+    /// Line is extended by half its width past its endpoints
+    Square,
+    /// A half disc is added past each endpoint
+    Round,
+}
+
+/// How lines are stroked by `Canvas::draw_line`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stroke {
+    /// width of the line in pixels
+    pub width: f64,
+    pub cap: LineCap,
+    /// alternating lengths of dashes and gaps in pixels, empty for a solid line
+    dash: Vec<f64>,
+    /// how far into the dash pattern lines start
+    pub dash_offset: f64,
+}
+
+impl Stroke {
+    /// A solid line `width` pixels wide
+    pub fn new(width: f64, cap: LineCap) -> Self {
+        Stroke {
+            width,
+            cap,
+            dash: vec![],
+            dash_offset: 0.,
+        }
+    }
+
+    /// Same stroke but dashed with `pattern`, starting `offset` pixels into the pattern
+    ///
+    /// Panics if a length in `pattern` is negative or not finite.
+    pub fn dashed(mut self, pattern: &[f64], offset: f64) -> Self {
+        assert!(
+            pattern.iter().all(|d| d.is_finite() && *d >= 0.),
+            "dash lengths must be finite and not negative"
+        );
+        self.dash = pattern.to_vec();
+        self.dash_offset = offset;
+        self
+    }
+
+    /// Whether this is a plain one pixel line, which can be drawn directly
+    pub fn is_hairline(&self) -> bool {
+        self.width <= 1. && self.cap == LineCap::Butt && !self.is_dashed()
+    }
+
+    /// Alternating lengths of dashes and gaps in pixels, empty for a solid line
+    pub fn dash(&self) -> &[f64] {
+        &self.dash
+    }
+
+    pub fn is_dashed(&self) -> bool {
+        self.dash.iter().sum::<f64>() > 0.
+    }
+}
+
+impl Default for Stroke {
+    fn default() -> Self {
+        Stroke::new(1., LineCap::Butt)
+    }
+}
+
+/// Split a line of length `len` into dashes according to `pattern`, starting `phase` pixels
+/// into the pattern.
+///
+/// Calls `dash` with the (start, end) distance along the line of every visible dash and
+/// returns the phase at the end of the line, so the pattern can continue onto the next line.
+pub(crate) fn split_dashes(
+    len: f64,
+    pattern: &[f64],
+    phase: f64,
+    mut dash: impl FnMut(f64, f64),
+) -> f64 {
+    // like svg, odd patterns are repeated to get an even number of dashes and gaps
+    let pattern: Vec<f64> = if pattern.len() % 2 == 1 {
+        pattern.iter().chain(pattern.iter()).copied().collect()
+    } else {
+        pattern.to_vec()
+    };
+    let total: f64 = pattern.iter().sum();
+    let phase = phase.rem_euclid(total);
+
+    // find where in the pattern the line starts
+    let (mut i, mut remaining) = (0, pattern[0]);
+    let mut skipped = 0.;
+    while skipped + remaining < phase {
+        skipped += remaining;
+        i = (i + 1) % pattern.len();
+        remaining = pattern[i];
+    }
+    remaining -= phase - skipped;
+
+    let mut pos = 0.;
+    while pos < len {
+        let step = remaining.min(len - pos);
+        // zero length dashes are kept on purpose, they are dots with round caps
+        if i % 2 == 0 && (step > 0. || pattern[i] == 0.) {
+            dash(pos, pos + step);
+        }
+        pos += step;
+        remaining -= step;
+        if remaining <= 0. {
+            i = (i + 1) % pattern.len();
+            remaining = pattern[i];
+        }
+    }
+
+    (phase + len).rem_euclid(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_dashes() {
+        let mut dashes = vec![];
+        let phase = split_dashes(10., &[3., 1.], 0., |a, b| dashes.push((a, b)));
+        assert_eq!(vec![(0., 3.), (4., 7.), (8., 10.)], dashes);
+        assert_eq!(2., phase);
+
+        // continues where the last line left off
+        dashes.clear();
+        split_dashes(4., &[3., 1.], phase, |a, b| dashes.push((a, b)));
+        assert_eq!(vec![(0., 1.), (2., 4.)], dashes);
+
+        // zero length dashes are dots
+        dashes.clear();
+        split_dashes(5., &[0., 2.], 0., |a, b| dashes.push((a, b)));
+        assert_eq!(vec![(0., 0.), (2., 2.), (4., 4.)], dashes);
+
+        // odd patterns alternate which part is a dash
+        dashes.clear();
+        split_dashes(6., &[2.], 0., |a, b| dashes.push((a, b)));
+        assert_eq!(vec![(0., 2.), (4., 6.)], dashes);
+    }
+
+    #[test]
+    #[should_panic(expected = "dash lengths must be finite and not negative")]
+    fn test_dashed_rejects_negative_lengths() {
+        Stroke::new(1., LineCap::Butt).dashed(&[3., -1.], 0.);
+    }
+}
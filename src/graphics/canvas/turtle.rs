@@ -1,4 +1,4 @@
-use super::Canvas;
+use super::{Canvas, Stroke};
 use crate::graphics::{utils::polar_to_xy, RGB};
 
 pub struct Turtle {
@@ -15,7 +15,7 @@ impl Turtle {
     /// Img will move into a Turtle, so any new bindings to the current instance of PPMImg will be invalid.
     ///
     /// And therefore only one Turtle is allowed at a time for an Img.
-    fn new(screen: Box<dyn Canvas>, x: f64, y: f64) -> Turtle {
+    pub fn new(screen: Box<dyn Canvas>, x: f64, y: f64) -> Turtle {
         Turtle {
             x,
             y,
@@ -28,9 +28,9 @@ impl Turtle {
     pub fn forward(&mut self, steps: i32) {
         let (x0, y0) = (self.x, self.y);
         let (dx, dy) = polar_to_xy(steps.into(), self.angle_deg);
-        let (x1, y1) = (x0 as f64 + dx, y0 as f64 + dy);
+        let (x1, y1) = (x0 + dx, y0 + dy);
         if self.pen_down {
-            self.img.draw_line(x0 as f64, y0 as f64, x1, y1);
+            self.img.draw_line(x0, y0, x1, y1);
         }
         self.x = x1;
         self.y = y1;
@@ -48,9 +48,18 @@ impl Turtle {
         self.img.get_fg_color()
     }
 
+    /// Set the stroke used for the lines the turtle draws
+    pub fn set_stroke(&mut self, stroke: Stroke) {
+        self.img.set_stroke(stroke);
+    }
+
+    pub fn get_stroke(&self) -> &Stroke {
+        self.img.get_stroke()
+    }
+
     pub fn move_to(&mut self, x: f64, y: f64) {
         if self.pen_down {
-            self.img.draw_line(self.x as f64, self.y as f64, x, y);
+            self.img.draw_line(self.x, self.y, x, y);
         }
         self.x = x;
         self.y = y;
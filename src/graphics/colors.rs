@@ -1,7 +1,6 @@
-use std::cmp;
 use std::convert;
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct RGB {
     pub red: u16,
     pub blue: u16,
@@ -26,56 +25,199 @@ impl RGB
             red, green, blue
         }
     }
+
+    /// Mix `other` over self, where `alpha` in [0, 1] is how much of `other` shows
+    pub fn blend(self, other: RGB, alpha: f64) -> Self
+    {
+        let mix = |a: u16, b: u16| (a as f64 + (b as f64 - a as f64) * alpha).round() as u16;
+        RGB {
+            red: mix(self.red, other.red),
+            green: mix(self.green, other.green),
+            blue: mix(self.blue, other.blue),
+        }
+    }
+
+    /// Brightness of the color as seen by the eye, with the Rec. 601 weights
+    pub fn luminance(self) -> u16
+    {
+        (0.299 * self.red as f64 + 0.587 * self.green as f64 + 0.114 * self.blue as f64).round()
+            as u16
+    }
+}
+
+/// A color with an alpha channel for compositing
+///
+/// Every channel, alpha included, goes from 0 to the depth of the image.
+/// Alpha of 0 is fully transparent, alpha of depth is opaque.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct RGBA {
+    pub red: u16,
+    pub green: u16,
+    pub blue: u16,
+    pub alpha: u16,
+}
+
+impl RGBA
+{
+    pub fn new(red: u16, green: u16, blue: u16, alpha: u16) -> Self
+    {
+        RGBA {
+            red, green, blue, alpha
+        }
+    }
+
+    pub fn from_rgb(color: RGB, alpha: u16) -> Self
+    {
+        RGBA::new(color.red, color.green, color.blue, alpha)
+    }
+
+    pub fn transparent() -> Self
+    {
+        RGBA::new(0, 0, 0, 0)
+    }
+
+    /// Drop the alpha channel
+    pub fn rgb(self) -> RGB
+    {
+        RGB::new(self.red, self.green, self.blue)
+    }
+
+    /// Porter–Duff "over": composite self on top of `under`
+    pub fn over(self, under: RGBA, depth: u16) -> Self
+    {
+        let max = depth as f64;
+        let (a_top, a_under) = (self.alpha as f64 / max, under.alpha as f64 / max);
+        let a_out = a_top + a_under * (1. - a_top);
+        if a_out <= 0. {
+            return RGBA::transparent();
+        }
+        let mix = |top: u16, btm: u16| {
+            ((top as f64 * a_top + btm as f64 * a_under * (1. - a_top)) / a_out).round() as u16
+        };
+        RGBA {
+            red: mix(self.red, under.red),
+            green: mix(self.green, under.green),
+            blue: mix(self.blue, under.blue),
+            alpha: (a_out * max).round() as u16,
+        }
+    }
+
+    /// Composite self over an opaque `bg` to get the final color
+    pub fn flatten(self, bg: RGB, depth: u16) -> RGB
+    {
+        self.over(RGBA::from_rgb(bg, depth), depth).rgb()
+    }
 }
 
 /// Hue, Saturation, Luminosity
 /// 
 /// range: [0, 1]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct HSL {
     pub h: f64,
     pub s: f64,
     pub l: f64,
 }
 
-impl convert::From<HSL> for RGB {
-    // https://en.wikipedia.org/wiki/HSL_and_HSV#HSL_to_RGB_alternative
-    fn from(hsl: HSL) -> RGB {
-        let hue = (hsl.h * 360.0).round() as i32;
-        let a = hsl.s * fmin2(hsl.l, 1.0 - hsl.l, 1000);
-        let f = |n| {
-            hsl.l
-                - a * fmax2(
-                    -1.0,
-                    cmp::min(
-                        cmp::min((n + hue / 30) % 12 - 3, 9 - (n + hue / 30) % 12),
-                        1,
-                    ) as f64,
-                    1000,
-                )
-        };
-        let (r, g, b) = (f(0).round() as i32, f(8).round() as i32, f(4).round() as i32);
-        assert!(r == 1 || r == 0);
-        assert!(g == 1 || g == 0);
-        assert!(b == 1 || b == 0);
-        RGB {
-            red: (f(0) * 255.0) as u16,
-            green: (f(8) * 255.0) as u16,
-            blue: (f(4) * 255.0) as u16,
+impl HSL
+{
+    /// `color`, with channels up to `depth`, as HSL
+    pub fn from_rgb(color: RGB, depth: u16) -> Self
+    {
+        let max_value = depth as f64;
+        let (r, g, b) = (
+            color.red as f64 / max_value,
+            color.green as f64 / max_value,
+            color.blue as f64 / max_value,
+        );
+        let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+        let l = (max + min) / 2.;
+        let d = max - min;
+        if d == 0. {
+            // gray, hue doesn't matter
+            return HSL { h: 0., s: 0., l };
         }
+        let s = d / (1. - (2. * l - 1.).abs());
+        let h = if max == r {
+            ((g - b) / d).rem_euclid(6.)
+        } else if max == g {
+            (b - r) / d + 2.
+        } else {
+            (r - g) / d + 4.
+        };
+        HSL { h: h / 6., s, l }
     }
-}
 
-fn fmax2(a: f64, b: f64, prec: i32) -> f64 {
-    let fprec = prec as f64;
-    cmp::max((a * fprec).round() as i32, (b * fprec).round() as i32) as f64 / fprec
+    /// As RGB with channels up to `depth`. Hue wraps around, so any value works.
+    pub fn to_rgb(self, depth: u16) -> RGB
+    {
+        // https://en.wikipedia.org/wiki/HSL_and_HSV#HSL_to_RGB
+        let c = (1. - (2. * self.l - 1.).abs()) * self.s;
+        let h = self.h.rem_euclid(1.) * 6.;
+        let x = c * (1. - (h % 2. - 1.).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.),
+            1 => (x, c, 0.),
+            2 => (0., c, x),
+            3 => (0., x, c),
+            4 => (x, 0., c),
+            _ => (c, 0., x),
+        };
+        let m = self.l - c / 2.;
+        let scale = |v: f64| ((v + m).clamp(0., 1.) * depth as f64).round() as u16;
+        RGB::new(scale(r), scale(g), scale(b))
+    }
 }
 
-fn fmin2(a: f64, b: f64, prec: i32) -> f64 {
-    let fprec = prec as f64;
-    cmp::min((a * fprec).round() as i32, (b * fprec).round() as i32) as f64 / fprec
+impl convert::From<HSL> for RGB {
+    /// Same as `hsl.to_rgb(255)`
+    fn from(hsl: HSL) -> RGB {
+        hsl.to_rgb(255)
+    }
 }
 
-fn fmin3(a: f64, b: f64, c: f64, prec: i32) -> f64 {
-    fmin2(fmin2(a, b, prec), c, prec)
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgba_over() {
+        let red = RGBA::new(255, 0, 0, 255);
+        let half_blue = RGBA::new(0, 0, 255, 128);
+
+        // opaque on top hides everything, transparent on top shows everything
+        assert_eq!(red, red.over(half_blue, 255));
+        assert_eq!(half_blue, RGBA::transparent().over(half_blue, 255));
+
+        assert_eq!(RGBA::new(127, 0, 128, 255), half_blue.over(red, 255));
+        assert_eq!(RGB::new(0, 0, 128), half_blue.flatten(RGB::gray(0), 255));
+
+        // two half transparent layers
+        let over = half_blue.over(half_blue, 255);
+        assert_eq!((0, 255, 192), (over.red, over.blue, over.alpha));
+    }
+
+    #[test]
+    fn test_hsl_round_trip() {
+        let orange = HSL::from_rgb(RGB::new(1000, 500, 0), 1000);
+        assert!((orange.h - 30. / 360.).abs() < 1e-9);
+        assert_eq!((1., 0.5), (orange.s, orange.l));
+
+        for &color in [
+            RGB::new(0, 0, 0),
+            RGB::new(100, 100, 100),
+            RGB::new(255, 0, 0),
+            RGB::new(12, 200, 90),
+            RGB::new(40, 20, 250),
+            RGB::new(250, 240, 3),
+        ]
+        .iter()
+        {
+            assert_eq!(color, HSL::from_rgb(color, 255).to_rgb(255));
+        }
+        // hue wraps around
+        let red = HSL { h: 1., s: 1., l: 0.5 };
+        assert_eq!(RGB::new(255, 0, 0), red.to_rgb(255));
+        assert_eq!(red.to_rgb(255), RGB::from(red));
+    }
+}
@@ -0,0 +1,239 @@
+//! Gradients for computed fills
+//!
+//! A gradient maps every point (x, y) to an offset, usually in [0, 1], with its geometry,
+//! and the offset to a color between its color stops. `shader` turns it into a
+//! `Fn(f64, f64) -> RGB` for `flood_fill_with_fn`, `fill_polygon_with_fn` and
+//! `PPMImg::fill_background_with_fn`.
+
+use std::f64::consts::PI;
+
+use crate::graphics::{HSL, RGB};
+
+/// How offsets are laid out over the canvas
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Geometry {
+    /// 0 on the line through `from` and 1 on the line through `to`, both perpendicular to
+    /// `from` -> `to`
+    Linear { from: (f64, f64), to: (f64, f64) },
+    /// 0 at `center`, 1 on the circle of `radius` around it
+    Radial { center: (f64, f64), radius: f64 },
+    /// Goes around `center` counter clockwise from 0 to 1, starting at `angle` degrees
+    /// counter clockwise from the x axis
+    Conic { center: (f64, f64), angle: f64 },
+}
+
+/// Color space colors are mixed in between stops
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Each of red, green and blue on its own
+    Rgb,
+    /// Hue, saturation and luminosity, going around the shorter way of the hue circle.
+    /// Keeps colors between saturated stops saturated.
+    Hsl,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub geometry: Geometry,
+    pub interpolation: Interpolation,
+    /// (offset, color), sorted by offset
+    stops: Vec<(f64, RGB)>,
+}
+
+impl Gradient {
+    /// Create a gradient without any color stops, mixing colors in RGB
+    pub fn new(geometry: Geometry) -> Self {
+        Gradient {
+            geometry,
+            interpolation: Interpolation::Rgb,
+            stops: vec![],
+        }
+    }
+
+    pub fn linear(from: (f64, f64), to: (f64, f64)) -> Self {
+        Self::new(Geometry::Linear { from, to })
+    }
+
+    pub fn radial(center: (f64, f64), radius: f64) -> Self {
+        Self::new(Geometry::Radial { center, radius })
+    }
+
+    pub fn conic(center: (f64, f64), angle: f64) -> Self {
+        Self::new(Geometry::Conic { center, angle })
+    }
+
+    /// Add a color stop at `offset`. Stops at the same offset make a hard edge there,
+    /// between the one added first and the one added last.
+    pub fn stop(mut self, offset: f64, color: RGB) -> Self {
+        let i = self.stops.partition_point(|&(o, _)| o <= offset);
+        self.stops.insert(i, (offset, color));
+        self
+    }
+
+    /// Mix colors with `interpolation`
+    pub fn interpolated(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Offset of (x, y) along the gradient, not limited to [0, 1]
+    pub fn offset(&self, x: f64, y: f64) -> f64 {
+        match self.geometry {
+            Geometry::Linear { from, to } => {
+                let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+                let len2 = dx * dx + dy * dy;
+                if len2 == 0. {
+                    0.
+                } else {
+                    ((x - from.0) * dx + (y - from.1) * dy) / len2
+                }
+            }
+            Geometry::Radial { center, radius } => (x - center.0).hypot(y - center.1) / radius,
+            Geometry::Conic { center, angle } => {
+                let theta = (y - center.1).atan2(x - center.0) - angle.to_radians();
+                theta.rem_euclid(2. * PI) / (2. * PI)
+            }
+        }
+    }
+
+    /// Color of the gradient at (x, y), with channels up to `depth`.
+    ///
+    /// Points before the first stop or after the last one get the color of that stop.
+    pub fn color_at(&self, x: f64, y: f64, depth: u16) -> RGB {
+        assert!(
+            !self.stops.is_empty(),
+            "A gradient needs at least one color stop"
+        );
+        let t = self.offset(x, y);
+
+        // first stop after t
+        let i = self.stops.partition_point(|&(o, _)| o <= t);
+        if i == 0 {
+            return self.stops[0].1;
+        }
+        if i == self.stops.len() {
+            return self.stops[i - 1].1;
+        }
+        let ((o0, c0), (o1, c1)) = (self.stops[i - 1], self.stops[i]);
+        let s = (t - o0) / (o1 - o0);
+
+        match self.interpolation {
+            Interpolation::Rgb => c0.blend(c1, s),
+            Interpolation::Hsl => {
+                let (mut a, mut b) = (HSL::from_rgb(c0, depth), HSL::from_rgb(c1, depth));
+                // grays have no hue, take the other one's so only saturation changes
+                if a.s == 0. {
+                    a.h = b.h;
+                }
+                if b.s == 0. {
+                    b.h = a.h;
+                }
+                let mut dh = b.h - a.h;
+                if dh > 0.5 {
+                    dh -= 1.;
+                } else if dh < -0.5 {
+                    dh += 1.;
+                }
+                HSL {
+                    h: a.h + dh * s,
+                    s: a.s + (b.s - a.s) * s,
+                    l: a.l + (b.l - a.l) * s,
+                }
+                .to_rgb(depth)
+            }
+        }
+    }
+
+    /// The gradient as a fill function for a canvas with colors up to `depth`
+    pub fn shader(&self, depth: u16) -> impl Fn(f64, f64) -> RGB + '_ {
+        move |x, y| self.color_at(x, y, depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{
+        canvas::{Connectivity, FillRule, FloodMode},
+        Canvas, PPMImg,
+    };
+
+    #[test]
+    fn test_geometry() {
+        let linear = Gradient::linear((10., 0.), (10., 20.));
+        assert_eq!(0.5, linear.offset(-100., 10.));
+        assert_eq!(-0.5, linear.offset(0., -10.));
+
+        let radial = Gradient::radial((0., 0.), 10.);
+        assert_eq!(0.5, radial.offset(3., 4.));
+
+        let conic = Gradient::conic((0., 0.), 90.);
+        assert_eq!(0., conic.offset(0., 5.));
+        assert_eq!(0.25, conic.offset(-5., 0.));
+        assert_eq!(0.75, conic.offset(5., 0.));
+    }
+
+    #[test]
+    fn test_color_stops() {
+        let (red, blue) = (RGB::new(255, 0, 0), RGB::new(0, 0, 255));
+        let g = Gradient::linear((0., 0.), (100., 0.))
+            .stop(1., blue)
+            .stop(0.5, RGB::gray(255))
+            .stop(0.25, red);
+        assert_eq!(red, g.color_at(-10., 0., 255));
+        assert_eq!(red, g.color_at(20., 0., 255));
+        assert_eq!(RGB::new(255, 128, 128), g.color_at(37.5, 0., 255));
+        assert_eq!(RGB::new(128, 128, 255), g.color_at(75., 0., 255));
+        assert_eq!(blue, g.color_at(150., 0., 255));
+
+        // a hard edge at 0.5
+        let g = Gradient::linear((0., 0.), (100., 0.))
+            .stop(0.5, red)
+            .stop(0.5, blue);
+        assert_eq!(red, g.color_at(49.9, 0., 255));
+        assert_eq!(blue, g.color_at(50., 0., 255));
+    }
+
+    #[test]
+    fn test_hsl_interpolation() {
+        let (red, blue) = (RGB::new(255, 0, 0), RGB::new(0, 0, 255));
+        let rgb = Gradient::linear((0., 0.), (1., 0.))
+            .stop(0., red)
+            .stop(1., blue);
+        let hsl = rgb.clone().interpolated(Interpolation::Hsl);
+
+        assert_eq!(RGB::new(128, 0, 128), rgb.color_at(0.5, 0., 255));
+        // red to blue the short way around is through magenta, at full saturation
+        assert_eq!(RGB::new(255, 0, 255), hsl.color_at(0.5, 0., 255));
+        // from gray, only saturation changes
+        let gray = Gradient::linear((0., 0.), (1., 0.))
+            .stop(0., RGB::gray(128))
+            .stop(1., red)
+            .interpolated(Interpolation::Hsl);
+        let mid = HSL::from_rgb(gray.color_at(0.5, 0., 255), 255);
+        assert!(mid.h.abs() < 1e-9 && (mid.s - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_gradient_fills() {
+        let g = Gradient::linear((0., 0.), (9., 0.))
+            .stop(0., RGB::gray(0))
+            .stop(1., RGB::gray(90));
+
+        let mut img = PPMImg::new(10, 10, 255);
+        img.fill_polygon_with_fn(
+            &[vec![(0., 0.), (10., 0.), (10., 5.), (0., 5.)]],
+            FillRule::NonZero,
+            &g.shader(255),
+        );
+        assert_eq!(Some(RGB::gray(40)), img.get_pixel(4, 2));
+        assert_eq!(Some(RGB::gray(0)), img.get_pixel(4, 7));
+
+        let red = RGB::new(255, 0, 0);
+        img.set_fg_color(red);
+        img.plot(9, 9);
+        img.flood_fill_with_fn(0, 9, FloodMode::Seed, Connectivity::Four, &g.shader(255));
+        assert_eq!(Some(RGB::gray(80)), img.get_pixel(8, 7));
+        assert_eq!(Some(red), img.get_pixel(9, 9));
+    }
+}
@@ -0,0 +1,301 @@
+//! Phong reflection model
+//!
+//! Lighting is computed in the same coordinate system as the polygons being rendered,
+//! with the viewer looking down the z axis from +z (view vector <0, 0, 1>).
+
+use crate::graphics::{
+    matrix::Matrix,
+    shadow::{ShadowMap, ShadowSettings},
+    vector::Vec3,
+    RGB,
+};
+
+/// How lighting is applied across a triangle
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Shading {
+    /// One color per triangle
+    Flat,
+    /// Colors computed at the vertices and interpolated
+    Gouraud,
+    /// Normals interpolated and lighting computed per pixel
+    Phong,
+}
+
+/// A light source with a color
+#[derive(Copy, Clone, Debug)]
+pub enum Light {
+    /// Light radiating from `position` in every direction
+    Point { position: Vec3, color: RGB },
+    /// Light coming from infinitely far away, traveling along `direction`
+    Directional { direction: Vec3, color: RGB },
+    /// Light radiating from `position` in a cone around `direction`, `angle` degrees wide
+    /// on each side of it
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        angle: f64,
+        color: RGB,
+    },
+}
+
+impl Light {
+    /// Unit vector from `point` towards the light
+    fn direction_from(&self, point: Vec3) -> Vec3 {
+        match *self {
+            Light::Point { position, .. } | Light::Spot { position, .. } => {
+                (position - point).normalized()
+            }
+            Light::Directional { direction, .. } => (-direction).normalized(),
+        }
+    }
+
+    fn color(&self) -> RGB {
+        match *self {
+            Light::Point { color, .. }
+            | Light::Directional { color, .. }
+            | Light::Spot { color, .. } => color,
+        }
+    }
+
+    /// Whether `point` is within the light's reach
+    fn reaches(&self, point: Vec3) -> bool {
+        match *self {
+            Light::Spot {
+                position,
+                direction,
+                angle,
+                ..
+            } => {
+                let cos = (point - position).normalized().dot(direction.normalized());
+                cos >= angle.to_radians().cos()
+            }
+            Light::Point { .. } | Light::Directional { .. } => true,
+        }
+    }
+}
+
+/// Reflection constants of a surface, each given per channel as (red, green, blue) in [0, 1]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Material {
+    /// ambient reflection
+    pub ka: (f64, f64, f64),
+    /// diffuse reflection
+    pub kd: (f64, f64, f64),
+    /// specular reflection
+    pub ks: (f64, f64, f64),
+    /// specular exponent, larger is shinier
+    pub shininess: f64,
+}
+
+impl Material {
+    pub fn new(
+        ka: (f64, f64, f64),
+        kd: (f64, f64, f64),
+        ks: (f64, f64, f64),
+        shininess: f64,
+    ) -> Self {
+        Material {
+            ka,
+            kd,
+            ks,
+            shininess,
+        }
+    }
+
+    /// Color of the material without any lighting: its diffuse reflection at full intensity
+    pub fn color(&self, depth: u16) -> RGB {
+        let channel = |k: f64| (k * depth as f64).round().max(0.).min(depth as f64) as u16;
+        RGB::new(channel(self.kd.0), channel(self.kd.1), channel(self.kd.2))
+    }
+
+    /// The material with its ambient and diffuse reflection tinted by `color`
+    pub fn tinted(&self, color: RGB, depth: u16) -> Material {
+        let (r, g, b) = (
+            color.red as f64 / depth as f64,
+            color.green as f64 / depth as f64,
+            color.blue as f64 / depth as f64,
+        );
+        Material {
+            ka: (self.ka.0 * r, self.ka.1 * g, self.ka.2 * b),
+            kd: (self.kd.0 * r, self.kd.1 * g, self.kd.2 * b),
+            ..*self
+        }
+    }
+}
+
+impl Default for Material {
+    /// A plain gray material
+    fn default() -> Self {
+        Material::new((0.1, 0.1, 0.1), (0.5, 0.5, 0.5), (0.5, 0.5, 0.5), 8.)
+    }
+}
+
+/// Ambient light and a set of light sources
+#[derive(Clone, Debug)]
+pub struct Lighting {
+    pub ambient: RGB,
+    pub lights: Vec<Light>,
+    /// shadow map of each light in `lights`, if it casts shadows
+    shadows: Vec<Option<ShadowMap>>,
+}
+
+impl Lighting {
+    pub fn new(ambient: RGB) -> Self {
+        Lighting {
+            ambient,
+            lights: vec![],
+            shadows: vec![],
+        }
+    }
+
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    /// Make every directional and spot light cast shadows of polygon matrix `scene`.
+    ///
+    /// `scene` must be in the same coordinates as the polygons that are rendered later on.
+    /// Call again whenever the scene or the lights change.
+    pub fn cast_shadows(&mut self, scene: &Matrix, settings: ShadowSettings) {
+        self.shadows = self
+            .lights
+            .iter()
+            .map(|light| ShadowMap::new(light, scene, settings))
+            .collect();
+    }
+
+    /// Stop casting shadows
+    pub fn clear_shadows(&mut self) {
+        self.shadows.clear();
+    }
+
+    /// Whether any light casts shadows
+    pub fn has_shadows(&self) -> bool {
+        self.shadows.iter().any(Option::is_some)
+    }
+
+    /// Compute the color of a surface at `point` with surface normal `normal`
+    ///
+    /// Result is clamped to [0, `depth`] on every channel
+    pub fn shade(&self, point: Vec3, normal: Vec3, material: &Material, depth: u16) -> RGB {
+        self.shade_shadowed(point, normal, material, depth, point)
+    }
+
+    /// Same as `shade`, but shadows are looked up at `shadow_point` instead of `point`.
+    ///
+    /// Lets flat shaded triangles be lit once from their center and still get sharp shadows.
+    pub fn shade_shadowed(
+        &self,
+        point: Vec3,
+        normal: Vec3,
+        material: &Material,
+        depth: u16,
+        shadow_point: Vec3,
+    ) -> RGB {
+        let n = normal.normalized();
+        let view = Vec3(0., 0., 1.);
+
+        let channel = |ambient: u16, ka: f64| ambient as f64 * ka;
+        let mut r = channel(self.ambient.red, material.ka.0);
+        let mut g = channel(self.ambient.green, material.ka.1);
+        let mut b = channel(self.ambient.blue, material.ka.2);
+
+        for (i, light) in self.lights.iter().enumerate() {
+            let l = light.direction_from(point);
+            let n_dot_l = n.dot(l);
+            if n_dot_l <= 0. || !light.reaches(point) {
+                // light is behind the surface
+                continue;
+            }
+            let visibility = match self.shadows.get(i) {
+                Some(Some(shadow)) => shadow.visibility(shadow_point, n, l),
+                _ => 1.,
+            };
+            // reflection of l about n
+            let reflect = n * (2. * n_dot_l) - l;
+            let spec = reflect.dot(view).max(0.).powf(material.shininess);
+
+            let color = light.color();
+            let channel =
+                |c: u16, kd: f64, ks: f64| c as f64 * visibility * (kd * n_dot_l + ks * spec);
+            r += channel(color.red, material.kd.0, material.ks.0);
+            g += channel(color.green, material.kd.1, material.ks.1);
+            b += channel(color.blue, material.kd.2, material.ks.2);
+        }
+
+        let clamp = |c: f64| c.round().max(0.).min(depth as f64) as u16;
+        RGB::new(clamp(r), clamp(g), clamp(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shade_ambient_only() {
+        let lighting = Lighting::new(RGB::gray(100));
+        let material = Material::new((0.5, 0.2, 0.), (1., 1., 1.), (1., 1., 1.), 4.);
+        let color = lighting.shade(Vec3(0., 0., 0.), Vec3(0., 0., 1.), &material, 255);
+        assert_eq!(RGB::new(50, 20, 0), color);
+    }
+
+    #[test]
+    fn test_shade_lights() {
+        let mut lighting = Lighting::new(RGB::gray(0));
+        let material = Material::new((0., 0., 0.), (0.5, 0.5, 0.5), (0.5, 0.5, 0.5), 4.);
+
+        // light straight on: full diffuse and full specular
+        lighting.add_light(Light::Directional {
+            direction: Vec3(0., 0., -1.),
+            color: RGB::new(200, 100, 0),
+        });
+        let color = lighting.shade(Vec3(0., 0., 0.), Vec3(0., 0., 1.), &material, 255);
+        assert_eq!(RGB::new(200, 100, 0), color);
+
+        // a point light behind the surface does not contribute
+        lighting.add_light(Light::Point {
+            position: Vec3(0., 0., -100.),
+            color: RGB::gray(255),
+        });
+        let color = lighting.shade(Vec3(0., 0., 0.), Vec3(0., 0., 5.), &material, 255);
+        assert_eq!(RGB::new(200, 100, 0), color);
+
+        // results are clamped to depth
+        let color = lighting.shade(Vec3(0., 0., 0.), Vec3(0., 0., 1.), &material, 150);
+        assert_eq!(RGB::new(150, 100, 0), color);
+    }
+
+    #[test]
+    fn test_shade_shadowed() {
+        let material = Material::new((0., 0., 0.), (1., 1., 1.), (0., 0., 0.), 1.);
+        let mut lighting = Lighting::new(RGB::gray(0));
+        lighting.add_light(Light::Spot {
+            position: Vec3(0., 0., 100.),
+            direction: Vec3(0., 0., -1.),
+            angle: 30.,
+            color: RGB::gray(255),
+        });
+
+        // a small occluder above the origin
+        let mut scene = Matrix::new_polygon_matrix();
+        scene.append_polygon((-10., -10., 50.), (10., -10., 50.), (0., 10., 50.));
+        scene.append_polygon((-100., -100., 0.), (100., -100., 0.), (0., 100., 0.));
+
+        let up = Vec3(0., 0., 1.);
+        let lit = lighting.shade(Vec3(0., 0., 0.), up, &material, 255);
+        assert_eq!(RGB::gray(255), lit);
+        // outside of the cone
+        let outside = lighting.shade(Vec3(90., 0., 0.), up, &material, 255);
+        assert_eq!(RGB::gray(0), outside);
+
+        lighting.cast_shadows(&scene, ShadowSettings::new(128, 1., 0));
+        let shadowed = lighting.shade(Vec3(0., 0., 0.), up, &material, 255);
+        assert_eq!(RGB::gray(0), shadowed);
+        let beside = lighting.shade(Vec3(30., 0., 0.), up, &material, 255);
+        assert!(beside.red > 200, "{:?}", beside);
+
+        lighting.clear_shadows();
+        assert_eq!(lit, lighting.shade(Vec3(0., 0., 0.), up, &material, 255));
+    }
+}
@@ -0,0 +1,177 @@
+//! Clipping in homogeneous clip space
+//!
+//! These should be used after a projection matrix is applied and before `perspective_divide`,
+//! so that nothing behind the camera (w <= 0) is ever divided.
+
+use super::Matrix;
+
+/// A point in clip space: [x, y, z, w]
+type Point4 = [f64; 4];
+
+/// Signed distances from the six frustum planes -w <= x, y, z <= w.
+/// A point is inside a plane when the distance is >= 0.
+const PLANES: [fn(&Point4) -> f64; 6] = [
+    |p| p[3] + p[0],
+    |p| p[3] - p[0],
+    |p| p[3] + p[1],
+    |p| p[3] - p[1],
+    |p| p[3] + p[2],
+    |p| p[3] - p[2],
+];
+
+/// Linear interpolation between two homogeneous points
+fn lerp(a: &Point4, b: &Point4, t: f64) -> Point4 {
+    [
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+        a[3] + (b[3] - a[3]) * t,
+    ]
+}
+
+/// Clip a convex polygon against every frustum plane with Sutherland–Hodgman.
+///
+/// Returns the vertices of the clipped polygon in the same winding order, or an empty vec
+/// if the polygon is completely outside.
+fn clip_polygon(mut poly: Vec<Point4>) -> Vec<Point4> {
+    for plane in PLANES.iter() {
+        if poly.is_empty() {
+            break;
+        }
+        let mut out = Vec::with_capacity(poly.len() + 1);
+        for (i, cur) in poly.iter().enumerate() {
+            let prev = &poly[(i + poly.len() - 1) % poly.len()];
+            let (dprev, dcur) = (plane(prev), plane(cur));
+            if dcur >= 0. {
+                if dprev < 0. {
+                    out.push(lerp(prev, cur, dprev / (dprev - dcur)));
+                }
+                out.push(*cur);
+            } else if dprev >= 0. {
+                out.push(lerp(prev, cur, dprev / (dprev - dcur)));
+            }
+        }
+        poly = out;
+    }
+    poly
+}
+
+/// Clip a line segment against every frustum plane (Liang–Barsky in homogeneous coordinates).
+///
+/// Returns the clipped endpoints, or None if the segment is completely outside.
+fn clip_segment(p0: &Point4, p1: &Point4) -> Option<(Point4, Point4)> {
+    let (mut t0, mut t1) = (0., 1.);
+    for plane in PLANES.iter() {
+        let (d0, d1) = (plane(p0), plane(p1));
+        if d0 < 0. && d1 < 0. {
+            return None;
+        }
+        if d0 < 0. {
+            t0 = f64::max(t0, d0 / (d0 - d1));
+        } else if d1 < 0. {
+            t1 = f64::min(t1, d0 / (d0 - d1));
+        }
+    }
+    if t0 > t1 {
+        None
+    } else {
+        Some((lerp(p0, p1, t0), lerp(p0, p1, t1)))
+    }
+}
+
+fn to_point4(row: &[f64]) -> Point4 {
+    [row[0], row[1], row[2], row[3]]
+}
+
+impl Matrix {
+    /// Clip a polygon matrix in clip space against the view frustum.
+    ///
+    /// Triangles that are partially outside are clipped and re-triangulated as a fan,
+    /// keeping their winding order and surface. Triangles completely outside are dropped.
+    pub fn clip_polygons(&self) -> Matrix {
+        assert_eq!(4, self.ncols, "Clipping needs homogeneous coordinates");
+        let mut clipped = Matrix::new_polygon_matrix();
+        for (t, tri) in self.data.chunks_exact(self.ncols * 3).enumerate() {
+            let poly = clip_polygon(tri.chunks_exact(4).map(to_point4).collect());
+            if poly.len() >= 3 {
+                clipped.copy_surface(self, t * 3);
+            }
+            for i in 1..poly.len().saturating_sub(1) {
+                for p in [poly[0], poly[i], poly[i + 1]].iter() {
+                    clipped.data.extend_from_slice(p);
+                }
+                clipped.nrows += 3;
+            }
+        }
+        clipped
+    }
+
+    /// Clip an edge matrix in clip space against the view frustum.
+    ///
+    /// Edges completely outside are dropped.
+    pub fn clip_edges(&self) -> Matrix {
+        assert_eq!(4, self.ncols, "Clipping needs homogeneous coordinates");
+        let mut clipped = Matrix::new_edge_matrix();
+        for edge in self.data.chunks_exact(self.ncols * 2) {
+            let (p0, p1) = (to_point4(&edge[0..4]), to_point4(&edge[4..8]));
+            if let Some((p0, p1)) = clip_segment(&p0, &p1) {
+                clipped.data.extend_from_slice(&p0);
+                clipped.data.extend_from_slice(&p1);
+                clipped.nrows += 2;
+            }
+        }
+        clipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::matrix::projections;
+
+    fn all_inside(m: &Matrix) -> bool {
+        m.iter_by_row()
+            .all(|p| PLANES.iter().all(|plane| plane(&to_point4(p)) >= -1e-9))
+    }
+
+    #[test]
+    fn test_clip_polygons_inside_untouched() {
+        let mut m = Matrix::new_polygon_matrix();
+        m.append_polygon((-0.5, -0.5, 0.), (0.5, -0.5, 0.), (0., 0.5, 0.));
+        let clipped = m.clip_polygons();
+        assert_eq!(m.data, clipped.data);
+    }
+
+    #[test]
+    fn test_clip_polygons_behind_camera() {
+        // a triangle stretching from in front of the camera to behind it
+        let mut m = Matrix::new_polygon_matrix();
+        m.append_polygon((-1., -1., -5.), (1., -1., -5.), (0., 1., 5.));
+        let m = m * projections::perspective(90., 1., 1., 100.);
+
+        let clipped = m.clip_polygons();
+        assert_eq!(0, clipped.nrows % 3);
+        assert!(clipped.nrows >= 3, "Part of the triangle is visible");
+        assert!(all_inside(&clipped));
+        assert!(clipped.iter_by_row().all(|p| p[3] > 0.), "Nothing left with w <= 0");
+
+        // completely behind the camera
+        let mut m = Matrix::new_polygon_matrix();
+        m.append_polygon((-1., -1., 5.), (1., -1., 5.), (0., 1., 5.));
+        let m = m * projections::perspective(90., 1., 1., 100.);
+        assert_eq!(0, m.clip_polygons().nrows);
+    }
+
+    #[test]
+    fn test_clip_edges() {
+        let mut m = Matrix::new_edge_matrix();
+        // crosses the right plane
+        m.append_edge(&[0., 0., 0., 3., 0., 0.]);
+        // completely outside
+        m.append_edge(&[2., 2., 0., 3., 3., 0.]);
+        let clipped = m.clip_edges();
+
+        assert_eq!(2, clipped.nrows);
+        assert_eq!(vec![0., 0., 0., 1., 1., 0., 0., 1.], clipped.data);
+    }
+}
@@ -0,0 +1,61 @@
+//! Face culling in world space
+
+use super::Matrix;
+use crate::graphics::{canvas::Culling, vector::Vec3};
+
+impl Matrix {
+    /// Keep only the triangles of a polygon matrix that `culling` draws, with their surfaces.
+    ///
+    /// Meant for world space, before a perspective projection: a `View::Eye` at the camera
+    /// sees the same faces the projected image shows.
+    pub fn cull(&self, culling: Culling) -> Matrix {
+        let mut culled = Matrix::new(0, self.ncols, vec![]);
+        for (t, tri) in self.data.chunks_exact(self.ncols * 3).enumerate() {
+            let v: Vec<Vec3> = tri
+                .chunks_exact(self.ncols)
+                .map(|r| Vec3(r[0], r[1], r[2]))
+                .collect();
+            if culling.facing(v[0], v[1], v[2]).is_some() {
+                culled.copy_surface(self, t * 3);
+                culled.data.extend_from_slice(tri);
+                culled.nrows += 3;
+            }
+        }
+        culled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{
+        canvas::{
+            culling::{CullFace, View},
+            Winding,
+        },
+        matrix::projections,
+    };
+
+    #[test]
+    fn test_cull_perspective_cube() {
+        // off to the right of a camera at the origin looking down -z
+        let mut cube = Matrix::new_polygon_matrix();
+        cube.add_box((3., 1., -4.), 2., 2., 2.);
+        let eye = View::Eye(Vec3(0., 0., 0.));
+        let culled = cube.cull(Culling::new(CullFace::Back, Winding::CounterClockwise, eye));
+
+        // the front and the left side are seen, which looking straight down -z would miss
+        assert_eq!(4 * 3, culled.nrows);
+        assert_eq!(2 * 3, cube.cull(Culling::default()).nrows);
+
+        // after the projection, the same faces are the ones facing the screen
+        let device = |m: &Matrix| {
+            let mut m = m * projections::perspective(90., 1., 1., 100.);
+            m.perspective_divide();
+            m.ndc_n1to1_to_device(100., 100.);
+            m
+        };
+        let (culled, cube) = (device(&culled), device(&cube));
+        assert_eq!(culled.data, cube.cull(Culling::default()).data);
+    }
+}
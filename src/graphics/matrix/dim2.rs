@@ -9,6 +9,7 @@ impl Matrix {
             nrows: 0,
             ncols: 4,
             data: vec![],
+            surfaces: vec![],
         }
     }
 
@@ -22,5 +23,25 @@ impl Matrix {
         self.nrows += 2;
     }
 
+    /// Split an edge matrix into paths of connected edges, as lists of (x, y) points.
+    ///
+    /// An edge continues the current path when it starts where the previous one ended,
+    /// otherwise it starts a new one.
+    pub fn paths(&self) -> Vec<Vec<(f64, f64)>> {
+        let mut paths: Vec<Vec<(f64, f64)>> = vec![];
+        let mut iter = self.iter_by_row();
+        while let Some(p0) = iter.next() {
+            let p1 = iter
+                .next()
+                .expect("Number of points must be a multiple of 2");
+            let (start, end) = ((p0[0], p0[1]), (p1[0], p1[1]));
+            match paths.last_mut() {
+                Some(path) if path.last() == Some(&start) => path.push(end),
+                _ => paths.push(vec![start, end]),
+            }
+        }
+        paths
+    }
+
     
 }
\ No newline at end of file
@@ -10,6 +10,7 @@ impl Matrix {
             nrows: 0,
             ncols: 4,
             data: vec![],
+            surfaces: vec![],
         }
     }
 
@@ -11,9 +11,15 @@ pub mod projections;
 pub mod transform;
 
 // impl on Matrix
+pub mod clipping;
+pub mod culling;
 pub mod dim2;
 pub mod dim3;
+pub mod normals;
 pub mod parametrics;
+pub mod surface;
+
+pub use surface::Surface;
 
 #[derive(Clone, Debug)]
 /// Row major rectangular matrix
@@ -22,6 +28,9 @@ pub struct Matrix {
     nrows: usize,
     ncols: usize,
     data: Vec<f64>,
+    /// (first row, surface) pairs, sorted by row. Each surface applies until the next one,
+    /// None going back to the canvas' own color
+    surfaces: Vec<(usize, Option<Surface>)>,
 }
 
 // constructor, get, set
@@ -43,6 +52,7 @@ impl Matrix {
             nrows,
             ncols,
             data: data.clone(),
+            surfaces: vec![],
         }
     }
 
@@ -52,7 +62,12 @@ impl Matrix {
             data.len(),
             "nrows * ncols must == data.len()"
         );
-        Matrix { nrows, ncols, data }
+        Matrix {
+            nrows,
+            ncols,
+            data,
+            surfaces: vec![],
+        }
     }
 
     pub fn get(&self, row: usize, col: usize) -> Option<f64> {
@@ -72,6 +87,7 @@ impl Matrix {
     pub fn clear(&mut self) {
         self.nrows = 0;
         self.data.clear();
+        self.surfaces.clear();
     }
 }
 
@@ -122,6 +138,8 @@ impl Matrix {
     }
 
     /// Multiplies self matrix by other matrix
+    ///
+    /// Rows of the result correspond to rows of self, so the result keeps the surfaces of self
     pub fn _mul(&self, other: &Self) -> Self {
         // self * other -> new
         assert_eq!(self.ncols, other.nrows, "ncols of m1 must == nrows of m2");
@@ -134,7 +152,10 @@ impl Matrix {
                 .zip(other.col_iter(c))
                 .fold(0.0, |sum, (a, b)| sum + a * b);
         }
-        Matrix::new(frows, fcols, fdata)
+        Matrix {
+            surfaces: self.surfaces.clone(),
+            ..Matrix::new(frows, fcols, fdata)
+        }
     }
 
     pub fn transposed_mul(&self, other: &Self) -> Self {
@@ -148,7 +169,10 @@ impl Matrix {
                 .zip(other.row_iter(r))
                 .fold(0.0, |sum, (a, b)| sum + a * b);
         }
-        Matrix::new(frows, fcols, fdata)
+        Matrix {
+            surfaces: other.surfaces.clone(),
+            ..Matrix::new(frows, fcols, fdata)
+        }
     }
 
     pub fn mul_mut_b(a: &Matrix, b: &mut Matrix) {
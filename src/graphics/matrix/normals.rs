@@ -0,0 +1,72 @@
+//! Vertex normals for smooth shading
+
+use super::Matrix;
+use crate::graphics::vector::Vec3;
+use std::collections::HashMap;
+
+/// Quantize a position so vertices that are meant to be shared (but differ by rounding
+/// error or signed zeros) map to the same key
+fn vertex_key(row: &[f64]) -> (i64, i64, i64) {
+    let q = |v: f64| (v * 1e6).round() as i64;
+    (q(row[0]), q(row[1]), q(row[2]))
+}
+
+impl Matrix {
+    /// Compute a normal for every vertex of a polygon matrix, in row order.
+    ///
+    /// Each vertex normal is the average of the unit face normals of all triangles
+    /// sharing that vertex position.
+    pub fn vertex_normals(&self) -> Vec<Vec3> {
+        let mut sums: HashMap<(i64, i64, i64), Vec3> = HashMap::new();
+        let mut keys = Vec::with_capacity(self.nrows);
+
+        for tri in self.data.chunks_exact(self.ncols * 3) {
+            let rows: Vec<&[f64]> = tri.chunks_exact(self.ncols).collect();
+            let v: Vec<Vec3> = rows.iter().map(|r| Vec3(r[0], r[1], r[2])).collect();
+            let face = (v[1] - v[0]).cross(v[2] - v[0]).normalized();
+
+            for row in rows {
+                let key = vertex_key(row);
+                let sum = sums.entry(key).or_insert(Vec3(0., 0., 0.));
+                *sum = *sum + face;
+                keys.push(key);
+            }
+        }
+
+        keys.iter().map(|key| sums[key].normalized()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sphere_normals_point_outwards() {
+        let mut m = Matrix::new_polygon_matrix();
+        m.add_sphere((10., 20., 30.), 100.);
+        let normals = m.vertex_normals();
+        assert_eq!(m.nrows, normals.len());
+
+        for (row, n) in m.iter_by_row().zip(normals) {
+            let radial = (Vec3(row[0], row[1], row[2]) - Vec3(10., 20., 30.)).normalized();
+            assert!(radial.dot(n) > 0.99, "Normal {:?} at {:?}", n, row);
+        }
+    }
+
+    #[test]
+    fn test_shared_vertex_normals_averaged() {
+        // two triangles folded along the x axis, facing +z and +y
+        let mut m = Matrix::new_polygon_matrix();
+        m.append_polygon((0., 0., 0.), (1., 0., 0.), (0., 1., 0.));
+        m.append_polygon((1., 0., 0.), (0., 0., 0.), (0., 0., 1.));
+        let normals = m.vertex_normals();
+
+        let half = 0.5f64.sqrt();
+        let shared = Vec3(0., half, half);
+        assert!((normals[0] - shared).mag() < 1e-9);
+        assert!((normals[4] - shared).mag() < 1e-9);
+        assert!((normals[2] - Vec3(0., 0., 1.)).mag() < 1e-9);
+        assert!((normals[5] - Vec3(0., 1., 0.)).mag() < 1e-9);
+    }
+}
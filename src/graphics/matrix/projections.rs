@@ -1,6 +1,7 @@
 use crate::graphics::{utils::mapper, matrix::Matrix};
 
 // https://developer.mozilla.org/en-US/docs/Web/API/WebGL_API/WebGL_model_view_projection#Perspective_matrix
+// WebGL lists matrices column by column, which is exactly the layout we need for row vectors
 
 /// Construct a perspective projection matrix based
 /// ## Arguments:
@@ -16,8 +17,8 @@ pub fn perspective(fov_rad: f64, aspect: f64, near: f64, far: f64) -> Matrix {
     Matrix::new(4, 4, vec![
         f / aspect, 0.,      0.,                             0.,
         0.,         f,       0.,                             0.,
-        0.,         0.,      (near + far) * range_inv,       near * far * range_inv * 2.,
-        0.,         0.,      -1.,                            0.,
+        0.,         0.,      (near + far) * range_inv,       -1.,
+        0.,         0.,      near * far * range_inv * 2.,    0.,
     ])
 
 
@@ -47,13 +48,17 @@ impl Matrix {
     /// Convert matrix in ndc coordinates to device coordinates
     ///
     /// This should be used only after perspective divide and before rendered onto the canvas
+    ///
+    /// y stays pointing up, so the canvas should have `invert_y` set for the image to be upright.
+    /// z is flipped so that nearer points have larger z, which is what the canvas depth buffer expects
     pub fn ndc_n1to1_to_device(&mut self, width: f64, height: f64) {
         let map_width = mapper(-1., 1., 0., width);
         let map_height = mapper(-1., 1., 0., height);
 
         for row in self.mut_iter_by_row() {
-            row[0] = map_width(-row[0]);
+            row[0] = map_width(row[0]);
             row[1] = map_height(row[1]);
+            row[2] = -row[2];
         }
 
     }
@@ -88,4 +93,48 @@ mod tests {
 
         display_edge_matrix(&model, true);
     }
+
+    #[test]
+    fn test_perspective_points() {
+        let (near, far) = (1., 100.);
+        let f = 1. / (0.5f64).tan();
+        let project = |m: &Matrix, x: f64, y: f64, z: f64| {
+            let mut p = Matrix::new(1, 4, vec![x, y, z, 1.])._mul(m);
+            p.perspective_divide();
+            p.row_iter(0).copied().collect::<Vec<_>>()
+        };
+        let close = |a: &[f64], b: &[f64]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-9);
+
+        let m = perspective(1., 2., near, far);
+        // the near and far planes end up at -1 and 1, and x and y shrink with distance
+        assert!(close(&project(&m, 0., 0., -near), &[0., 0., -1.]));
+        assert!(close(&project(&m, 0., 0., -far), &[0., 0., 1.]));
+        let p = project(&m, 4., 2., -4.);
+        assert!(close(&p[..2], &[f / 2., f / 2.]));
+
+        // what is in front of the camera stays in the view volume, what is behind it is clipped
+        let triangle = |z: f64| {
+            let mut tri = Matrix::new_polygon_matrix();
+            tri.append_polygon((-1., -1., z), (1., -1., z), (0., 1., z));
+            tri._mul(&m).clip_polygons().iter_by_row().len()
+        };
+        assert_eq!(3, triangle(-4.));
+        assert_eq!(0, triangle(4.));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_ndc_to_device() {
+        let mut m = Matrix::new(3, 4, vec![
+            -1., -1., 0.5, 1.,
+            1., 1., -1., 1.,
+            0., 0.5, 0., 1.,
+        ]);
+        m.ndc_n1to1_to_device(500., 250.);
+        let row = |r| m.row_iter(r).copied().collect::<Vec<_>>();
+        // x and y keep their directions, only z is flipped
+        assert_eq!(vec![0., 0., -0.5, 1.], row(0));
+        assert_eq!(vec![500., 250., 1., 1.], row(1));
+        assert_eq!(vec![250., 187.5, 0., 1.], row(2));
+    }
 }
\ No newline at end of file
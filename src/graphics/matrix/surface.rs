@@ -0,0 +1,86 @@
+//! Colors and materials attached to ranges of a polygon matrix
+//!
+//! A surface is set once and applies to every triangle appended after it, so shapes can be
+//! given their own look as they are added:
+//!
+//! ```ignore
+//! m.set_surface(Surface::Color(RGB::new(255, 0, 0)));
+//! m.add_sphere((0., 0., 0.), 50.);
+//! m.set_surface(Surface::Material(Material::default()));
+//! m.add_box((0., 0., 0.), 10., 10., 10.);
+//! ```
+
+use super::Matrix;
+use crate::graphics::{lighting::Material, RGB};
+
+/// What a range of triangles is drawn with
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Surface {
+    /// A plain color. When lit, it tints the ambient and diffuse reflection of the material
+    Color(RGB),
+    /// Reflection constants used when lit. When unlit, drawn with its diffuse color
+    Material(Material),
+}
+
+impl Matrix {
+    /// Use `surface` for every row appended from now on
+    pub fn set_surface(&mut self, surface: Surface) {
+        self.push_surface(Some(surface));
+    }
+
+    /// Go back to drawing rows appended from now on with the canvas' own color or material
+    pub fn unset_surface(&mut self) {
+        self.push_surface(None);
+    }
+
+    /// Surface that applies to `row`, if any
+    pub fn surface_at(&self, row: usize) -> Option<Surface> {
+        let i = self.surfaces.partition_point(|&(start, _)| start <= row);
+        if i == 0 {
+            None
+        } else {
+            self.surfaces[i - 1].1
+        }
+    }
+
+    fn push_surface(&mut self, surface: Option<Surface>) {
+        match self.surfaces.last_mut() {
+            // nothing was appended under the previous surface
+            Some((start, last)) if *start == self.nrows => *last = surface,
+            _ => self.surfaces.push((self.nrows, surface)),
+        }
+    }
+
+    /// Continue the surface of row `row` of `other` on rows appended to self
+    pub(crate) fn copy_surface(&mut self, other: &Matrix, row: usize) {
+        let surface = other.surface_at(row);
+        if self.surfaces.last().and_then(|&(_, s)| s) != surface {
+            self.push_surface(surface);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::matrix::transform;
+
+    #[test]
+    fn test_surfaces_follow_rows() {
+        let red = RGB::new(255, 0, 0);
+        let mut m = Matrix::new_polygon_matrix();
+        m.add_box((0., 0., 0.), 1., 1., 1.);
+        m.set_surface(Surface::Color(red));
+        m.add_box((2., 0., 0.), 1., 1., 1.);
+        m.unset_surface();
+        m.append_polygon((0., 0., 0.), (1., 0., 0.), (0., 1., 0.));
+
+        // surviving a transformation
+        let m = m * transform::mv(10., 0., 0.) * transform::rotatey(30.);
+
+        assert!(m.surface_at(35).is_none());
+        assert_eq!(Some(Surface::Color(red)), m.surface_at(36));
+        assert_eq!(Some(Surface::Color(red)), m.surface_at(71));
+        assert!(m.surface_at(72).is_none());
+    }
+}
@@ -1,4 +1,5 @@
 use super::Matrix;
+use crate::graphics::vector::Vec3;
 
 // generate transformation matrices
 /// Generate a translation matrix with (dx, dy, dz)
@@ -71,6 +72,24 @@ pub fn rotatez(angle_deg: f64) -> Matrix {
     m
 }
 
+/// Generate a view matrix for a camera at `eye` looking along `forward`
+///
+/// Afterwards the camera is at the origin looking down -z, which is what the projections expect
+#[rustfmt::skip]
+pub fn look_at(eye: Vec3, forward: Vec3) -> Matrix {
+    let f = forward.normalized();
+    // any up vector works as long as it is not parallel to forward
+    let up = if f.1.abs() < 0.99 { Vec3(0., 1., 0.) } else { Vec3(0., 0., 1.) };
+    let r = f.cross(up).normalized();
+    let u = r.cross(f);
+    Matrix::new(4, 4, vec![
+        r.0,         u.0,         -f.0,       0.,
+        r.1,         u.1,         -f.1,       0.,
+        r.2,         u.2,         -f.2,       0.,
+        -eye.dot(r), -eye.dot(u), eye.dot(f), 1.,
+    ])
+}
+
 impl Matrix {
     /// Correct edges after projection by dividing all values of point by w
     pub fn perspective_divide(&mut self) {
@@ -1,9 +1,23 @@
 #![allow(dead_code)]
 
+pub mod bmp;
 pub mod colors;
+pub mod gradient;
+pub mod lighting;
 pub mod matrix;
 pub mod parametrics;
 pub mod parser;
+pub mod png;
+pub mod pnm;
+pub mod ppm;
+pub mod sampling;
+pub mod shadow;
+pub mod svg;
+pub mod terminal;
+#[cfg(test)]
+mod test_utils;
+pub mod tga;
+pub mod tiled;
 pub mod canvas;
 pub mod utils;
 pub mod vector;
@@ -13,26 +27,42 @@ use std::convert::TryInto;
 use std::{
     fmt::Debug,
     io::{self, prelude::Write},
+    path::Path,
 };
 
 // re-exports
-pub use colors::{HSL, RGB};
+pub use colors::{HSL, RGB, RGBA};
 pub use matrix::Matrix;
-pub use canvas::Canvas;
+pub use sampling::Filter;
+pub use canvas::{Canvas, Culling, LineMode, Stroke};
 
 // internal use
+use canvas::{average_samples, from_samples};
+use pnm::Halftone;
 use utils::create_file;
 
 pub struct PPMImg {
+    // size of the drawing surface, `samples` times the size of the output image
     height: u32,
     width: u32,
     depth: u16, // max = 2^16
+    /// supersampling factor in both x and y, 1 for none
+    samples: u32,
+    filter: Filter,
     pub x_wrap: bool,
     pub y_wrap: bool,
     pub invert_y: bool,
+    pub line_mode: LineMode,
+    pub stroke: Stroke,
+    pub culling: Culling,
     pub fg_color: RGB,
+    /// opacity of what's drawn, from 0 (transparent) to depth (opaque)
+    pub fg_alpha: u16,
     pub bg_color: RGB,
-    data: Vec<RGB>,
+    /// pixels start out transparent and are composited over `bg_color` when written
+    data: Vec<RGBA>,
+    /// depth of what's drawn at each pixel, larger is nearer
+    zbuf: Vec<f64>,
 }
 
 /// Two images are eq iff their dimensions, depth, and image data are eq
@@ -70,60 +100,119 @@ impl PPMImg {
             height,
             width,
             depth,
+            samples: 1,
+            filter: Filter::Box,
             x_wrap: false,
             y_wrap: false,
             invert_y: false,
+            line_mode: LineMode::Bresenham,
+            stroke: Stroke::default(),
+            culling: Culling::default(),
             fg_color: RGB::gray(depth),
+            fg_alpha: depth,
             bg_color,
-            data: vec![bg_color; (width * height).try_into().unwrap()],
+            data: vec![RGBA::transparent(); (width * height).try_into().unwrap()],
+            zbuf: vec![f64::NEG_INFINITY; (width * height).try_into().unwrap()],
         }
     }
 
+    /// Create a new PPMImg that is drawn at `samples` times the resolution in both x and y,
+    /// and filtered down to `height` by `width` with `filter` when written.
+    ///
+    /// It is drawn on in the coordinates of the written image, like any other PPMImg.
+    /// Lines and fills are rasterized on the finer grid of samples (see `Canvas::samples`),
+    /// while single pixels, circles and flood fills cover all the samples of a pixel.
+    pub fn new_supersampled(
+        height: u32,
+        width: u32,
+        depth: u16,
+        samples: u32,
+        filter: Filter,
+    ) -> PPMImg {
+        assert!(samples > 0, "Need at least one sample per pixel");
+        let mut img = Self::new(height * samples, width * samples, depth);
+        img.samples = samples;
+        img.filter = filter;
+        img
+    }
+
+    /// Size of the written image as (width, height)
+    pub fn output_size(&self) -> (u32, u32) {
+        (self.width / self.samples, self.height / self.samples)
+    }
+
     pub fn write_binary(&self, filepath: &str) -> io::Result<()> {
-        let mut file = create_file(filepath);
-        writeln!(file, "P6")?;
-        writeln!(file, "{} {} {}", self.width, self.height, self.depth)?;
-        if self.depth < 256 {
-            for t in self.data.iter() {
-                file.write(&[t.green as u8])?;
-                file.write(&[t.green as u8])?;
-                file.write(&[t.blue as u8])?;
-            }
-        } else {
-            for t in self.data.iter() {
-                file.write_all(&(t.red.to_be_bytes()))?;
-                file.write_all(&(t.green.to_be_bytes()))?;
-                file.write_all(&(t.blue.to_be_bytes()))?;
+        self.write_binary_to(&mut create_file(filepath)?)
+    }
+
+    /// Write the image to `out` as a binary (P6) PPM,
+    /// with two bytes per channel if `depth` doesn't fit in one
+    pub fn write_binary_to(&self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = self.output_size();
+        writeln!(out, "P6")?;
+        writeln!(out, "{} {} {}", width, height, self.depth)?;
+        let mut bytes = Vec::with_capacity((width * height) as usize * 6);
+        for t in self.pixels() {
+            for &c in [t.red, t.green, t.blue].iter() {
+                if self.depth < 256 {
+                    bytes.push(c as u8);
+                } else {
+                    bytes.extend_from_slice(&c.to_be_bytes());
+                }
             }
         }
-
-        file.flush()?;
-        Ok(())
+        out.write_all(&bytes)?;
+        out.flush()
     }
+
     pub fn write_ascii(&self, filepath: &str) -> io::Result<()> {
-        let mut file = create_file(filepath);
-        writeln!(file, "P3")?;
-        writeln!(file, "{} {} {}", self.width, self.height, self.depth)?;
-        for t in self.data.iter() {
-            writeln!(file, "{} {} {}", t.red, t.green, t.blue)?;
+        self.write_ascii_to(&mut create_file(filepath)?)
+    }
+
+    /// Write the image to `out` as a plain (P3) PPM, one pixel per line
+    pub fn write_ascii_to(&self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = self.output_size();
+        writeln!(out, "P3")?;
+        writeln!(out, "{} {} {}", width, height, self.depth)?;
+        for t in self.pixels() {
+            writeln!(out, "{} {} {}", t.red, t.green, t.blue)?;
+        }
+        out.flush()
+    }
+
+    /// Write the image in the format picked by the extension of `filepath`:
+    /// PNG for ".png", BMP for ".bmp", run length encoded TGA for ".tga", binary PGM for
+    /// ".pgm", binary PBM thresholded at half the depth for ".pbm" and binary PPM for
+    /// anything else
+    pub fn save(&self, filepath: &str) -> io::Result<()> {
+        let extension = Path::new(filepath)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("png") => self.write_png(filepath),
+            Some("bmp") => self.write_bmp(filepath),
+            Some("tga") => self.write_tga(filepath, true),
+            Some("pgm") => self.write_pgm_binary(filepath),
+            Some("pbm") => self.write_pbm_binary(filepath, Halftone::Threshold(self.depth / 2 + 1)),
+            _ => self.write_binary(filepath),
         }
-        file.flush()?;
-        Ok(())
     }
 }
 
 // clear
 impl PPMImg {
     pub fn clear(&mut self) {
-        let bg = self.bg_color;
         for d in self.data.iter_mut() {
-            *d = bg;
+            *d = RGBA::transparent();
+        }
+        for z in self.zbuf.iter_mut() {
+            *z = f64::NEG_INFINITY;
         }
     }
 }
 
 impl PPMImg {
-    /// Returns Some(index) if index exists. Otherwise None.
+    /// Returns Some(index) of sample (x, y) if it exists. Otherwise None.
     fn index(&self, x: i32, y: i32) -> Option<usize> {
         let (width, height) = (
             self.width.try_into().unwrap(),
@@ -160,7 +249,7 @@ impl PPMImg {
 
         // invert y based on config
         let y = if self.invert_y {
-            self.width as i32 - y - 1
+            self.height as i32 - y - 1
         } else {
             y
         };
@@ -169,15 +258,100 @@ impl PPMImg {
         Some((y * self.width as i32 + x).try_into().unwrap())
     }
 
+    /// Final color of every pixel of the output image, row by row.
+    ///
+    /// Pixels are flattened against `bg_color` and filtered down if supersampled.
+    fn pixels(&self) -> Vec<RGB> {
+        let flat: Vec<RGB> = self
+            .data
+            .iter()
+            .map(|p| p.flatten(self.bg_color, self.depth))
+            .collect();
+        sampling::downsample(&flat, self.width, self.height, self.samples, self.filter)
+    }
+
+    /// Rows of the output image from the bottom up, each as 8 bit blue, green and red for
+    /// every pixel, the way BMP and TGA store them.
+    ///
+    /// `pixels` is already top down whether or not `invert_y` is set, so this only reverses it.
+    fn bgr_rows_bottom_up(&self) -> Vec<Vec<u8>> {
+        let width = self.output_size().0.max(1) as usize;
+        let scale = |c: u16| (c as f64 * u8::MAX as f64 / self.depth as f64).round() as u8;
+        self.pixels()
+            .chunks_exact(width)
+            .rev()
+            .map(|row| {
+                let mut bgr = Vec::with_capacity(row.len() * 3);
+                for p in row {
+                    bgr.extend_from_slice(&[scale(p.blue), scale(p.green), scale(p.red)]);
+                }
+                bgr
+            })
+            .collect()
+    }
+
+    /// Composite `color` over the pixel at `index`
+    fn composite(&mut self, index: usize, color: RGBA) {
+        self.data[index] = color.over(self.data[index], self.depth);
+    }
+
+    /// The fg color with `fg_alpha`
+    fn fg_rgba(&self) -> RGBA {
+        RGBA::from_rgb(self.fg_color, self.fg_alpha)
+    }
+
+}
+
+impl PPMImg {
+    /// Plot every sample of pixel (x, y)
+    fn plot_block(&mut self, x: i32, y: i32, z: Option<f64>) {
+        let s = self.samples as i32;
+        for sy in 0..s {
+            for sx in 0..s {
+                self.plot_sample(x.saturating_mul(s) + sx, y.saturating_mul(s) + sy, z);
+            }
+        }
+    }
 }
 
 impl Canvas for PPMImg {
     /// plot a point on this PPMImg at (x, y)
     fn plot(&mut self, x: i32, y: i32) -> () {
+        self.plot_block(x, y, None);
+    }
+    /// plot a point at (x, y) if z is nearer than the current depth there
+    fn plot_z(&mut self, x: i32, y: i32, z: f64) {
+        self.plot_block(x, y, Some(z));
+    }
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+    fn plot_sample(&mut self, x: i32, y: i32, z: Option<f64>) {
         if let Some(index) = self.index(x, y) {
-            self.data[index] = self.fg_color;
+            match z {
+                Some(z) if z <= self.zbuf[index] => (),
+                Some(z) => {
+                    self.zbuf[index] = z;
+                    self.composite(index, self.fg_rgba());
+                }
+                None => self.composite(index, self.fg_rgba()),
+            }
         }
     }
+    fn get_sample(&self, x: i32, y: i32) -> Option<RGB> {
+        self.index(x, y)
+            .map(|index| self.data[index].flatten(self.bg_color, self.depth))
+    }
+    /// plot a partially covered sample by scaling the fg alpha with `coverage`
+    fn plot_coverage(&mut self, x: i32, y: i32, z: Option<f64>, coverage: f64) {
+        if coverage <= 0. {
+            return;
+        }
+        let fg_alpha = self.fg_alpha;
+        self.fg_alpha = (fg_alpha as f64 * coverage.min(1.)).round() as u16;
+        self.plot_sample(x, y, z);
+        self.fg_alpha = fg_alpha;
+    }
     fn set_fg_color(&mut self, color: RGB) {
         self.fg_color = color;
     }
@@ -190,21 +364,47 @@ impl Canvas for PPMImg {
     fn get_bg_color(&self) -> RGB {
         self.bg_color
     }
+    fn set_stroke(&mut self, stroke: Stroke) {
+        self.stroke = stroke;
+    }
+    fn get_stroke(&self) -> &Stroke {
+        &self.stroke
+    }
     fn width(&self) -> u32 {
-        self.width
+        self.output_size().0
     }
     fn height(&self) -> u32 {
-        self.height
+        self.output_size().1
+    }
+    fn depth(&self) -> u16 {
+        self.depth
+    }
+    fn wrap(&self) -> (bool, bool) {
+        (self.x_wrap, self.y_wrap)
+    }
+    fn line_mode(&self) -> LineMode {
+        self.line_mode
+    }
+    fn culling(&self) -> Culling {
+        self.culling
+    }
+    fn get_pixel(&self, x: i32, y: i32) -> Option<RGB> {
+        if self.samples == 1 {
+            self.get_sample(x, y)
+        } else {
+            average_samples(self, x, y)
+        }
     }
 }
 
-
 // this will stay here during trait refactor, since it has assumption about the internal data structure for Img
 impl PPMImg {
     /// Fill an area in img with color calculated by `fill`,
     /// starting at (x, y) and ending when encounters bound color `bound`.
     /// 
     /// Note: This function uses the fact that PPMImg is stored as a `Vec` with an `index` method.
+    /// Unlike `Canvas::flood_fill_with_fn`, pixels are filled opaque and the fill wraps
+    /// around the edges like the rest of the drawing when `x_wrap` or `y_wrap` is set.
     pub fn bound4_fill_with_fn(
         &mut self,
         x: i32,
@@ -212,18 +412,21 @@ impl PPMImg {
         fill: impl Fn(f64, f64) -> RGB,
         bound: RGB,
     ) {
-        let mut points = vec![(x, y)];
+        // filled sample by sample, starting from the middle of pixel (x, y)
+        let s = self.samples as i32;
+        let to_pixel = |v: i32| from_samples(s as f64, v as f64);
+        let mut points = vec![(x.saturating_mul(s) + s / 2, y.saturating_mul(s) + s / 2)];
         while let Some((x, y)) = points.pop() {
             if let Some(index) = self.index(x, y) {
-                let color = self.data[index];
+                let color = self.data[index].flatten(self.bg_color, self.depth);
                 if color == bound {
                     continue;
                 }
-                let fcolor = fill(x as f64, y as f64);
+                let fcolor = fill(to_pixel(x), to_pixel(y));
                 if color == fcolor {
                     continue;
                 }
-                self.data[index] = fcolor;
+                self.data[index] = RGBA::from_rgb(fcolor, self.depth);
                 points.push((x + 1, y));
                 points.push((x, y + 1));
                 points.push((x - 1, y));
@@ -232,4 +435,120 @@ impl PPMImg {
             assert!(points.len() <= (self.width * self.height).try_into().unwrap());
         }
     }
+
+    /// Fill the background behind everything drawn so far with color calculated by `fill`
+    /// at each pixel (x, y). Depths are left alone, the background is behind everything.
+    pub fn fill_background_with_fn(&mut self, fill: impl Fn(f64, f64) -> RGB) {
+        let s = self.samples as f64;
+        for y in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                let index = self.index(x, y).unwrap();
+                let (px, py) = (from_samples(s, x as f64), from_samples(s, y as f64));
+                let bg = RGBA::from_rgb(fill(px, py), self.depth);
+                self.data[index] = self.data[index].over(bg, self.depth);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_to() {
+        let mut img = PPMImg::new(1, 2, 255);
+        img.set_fg_color(RGB::new(1, 2, 3));
+        img.plot(1, 0);
+
+        let mut out = vec![];
+        img.write_binary_to(&mut out).unwrap();
+        assert_eq!(b"P6\n2 1 255\n\0\0\0\x01\x02\x03".to_vec(), out);
+
+        let mut out = vec![];
+        img.write_ascii_to(&mut out).unwrap();
+        assert_eq!(
+            "P3\n2 1 255\n0 0 0\n1 2 3\n",
+            String::from_utf8(out).unwrap()
+        );
+
+        // two bytes per channel, read back
+        let mut img = PPMImg::new(1, 1, 1000);
+        img.set_fg_color(RGB::new(1000, 500, 7));
+        img.plot(0, 0);
+        let mut out = vec![];
+        img.write_binary_to(&mut out).unwrap();
+        assert_eq!(b"P6\n1 1 1000\n\x03\xe8\x01\xf4\0\x07", &out[..]);
+        assert!(img.pixels() == PPMImg::read_ppm_from(&mut &out[..]).unwrap().pixels());
+    }
+
+    #[test]
+    fn test_write_errors() {
+        let img = PPMImg::new(1, 1, 255);
+        let err = img.write_binary("this/dir/does/not/exist.ppm").unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.kind());
+        assert!(err
+            .to_string()
+            .contains("Could not create this/dir/does/not/exist.ppm"));
+    }
+
+    #[test]
+    fn test_invert_y() {
+        // taller than it is wide, so rows have to be flipped by the height
+        let mut img = PPMImg::new(3, 2, 255);
+        img.invert_y = true;
+        assert_eq!(Some(4), img.index(0, 0));
+        assert_eq!(Some(1), img.index(1, 2));
+        assert_eq!(None, img.index(0, 3));
+    }
+
+    #[test]
+    fn test_bound4_fill_wraps() {
+        let (red, blue) = (RGB::new(255, 0, 0), RGB::new(0, 0, 255));
+        let mut img = PPMImg::new(20, 20, 255);
+        img.set_fg_color(red);
+        img.draw_line(5., 0., 5., 10.);
+        img.draw_line(8., 0., 8., 10.);
+        img.draw_line(0., 10., 19., 10.);
+        img.x_wrap = true;
+        img.fg_alpha = 0;
+        img.bound4_fill_with_fn(2, 2, |_, _| blue, red);
+
+        // opaque whatever the fg alpha, and across the left edge up to the line at x = 8
+        assert_eq!(Some(blue), img.get_pixel(0, 5));
+        assert_eq!(Some(blue), img.get_pixel(19, 5));
+        assert_eq!(Some(RGB::gray(0)), img.get_pixel(6, 5));
+    }
+
+    #[test]
+    fn test_supersampled_coverage() {
+        let draw = |img: &mut PPMImg| {
+            img.invert_y = true;
+            img.fill_triangle((4., 4., 0.), (35., 8., 0.), (12., 30., 0.));
+            img.draw_line(2., 37., 37., 33.);
+            img.stroke = Stroke::new(5., canvas::LineCap::Butt);
+            img.draw_line(30., 20., 30., 36.);
+        };
+        let mut plain = PPMImg::new(40, 40, 255);
+        draw(&mut plain);
+        let mut supersampled = PPMImg::new_supersampled(40, 40, 255, 4, Filter::Box);
+        draw(&mut supersampled);
+        assert_eq!((40, 40), (supersampled.width(), supersampled.height()));
+
+        // the same shapes in the same places, only with softer edges
+        let coverage = |img: &PPMImg| -> Vec<f64> {
+            img.pixels().iter().map(|p| p.red as f64 / 255.).collect()
+        };
+        let (plain, supersampled) = (coverage(&plain), coverage(&supersampled));
+        let total = |c: &[f64]| c.iter().sum::<f64>();
+        // whole pixels are a little generous along the edges
+        assert!((total(&plain) - total(&supersampled)).abs() / total(&plain) < 0.1);
+        let diff: f64 = plain
+            .iter()
+            .zip(&supersampled)
+            .map(|(a, b)| (a - b).abs())
+            .sum();
+        assert!(diff / total(&plain) < 0.15, "{}", diff / total(&plain));
+        assert!(supersampled.iter().any(|&c| c > 0. && c < 1.));
+    }
 }
@@ -25,11 +25,13 @@
 ///          apply: apply the current transformation matrix to the edge matrix
 ///          display: clear the screen, then
 ///                   draw the lines of the edge matrix to the screen
-///                   display the screen
+///                   display the screen (on the terminal if there is no image viewer)
 ///          save: clear the screen, then
 ///                draw the lines of the edge matrix to the screen
 ///                save the screen to a file -
-///                takes 1 argument (file name)
+///                takes 1 argument (file name), the format is picked by its extension
+///                (.png for PNG, .bmp for BMP, .tga for TGA, .pgm for grayscale PGM,
+///                .pbm for black and white PBM, PPM otherwise)
 ///          quit: end parsing
 /// See the file script for an example of the file format
 ///
@@ -37,7 +39,6 @@
 use std::{
     fs::File,
     io::{self, prelude::*, BufReader},
-    process::Command,
 };
 
 use crate::graphics::{matrix::transform, utils, Matrix, PPMImg, Canvas};
@@ -139,17 +140,8 @@ impl DWScript {
                     self.img.render_edge_matrix(&self.edges);
                     self.img.render_polygon_matrix(&self.polygons);
                     self.img
-                        .write_binary(dline.as_str())
+                        .save(dline.as_str())
                         .expect("Error writing to file");
-
-                    // if a .png is wanted, then convert to .png
-                    if dline.ends_with(".png") {
-                        Command::new("convert")
-                            .arg(dline.as_str())
-                            .arg(dline.as_str())
-                            .spawn()
-                            .unwrap();
-                    }
                 }
                 "circle" => {
                     let (_dnum, dline) = getline_or_error(&mut lines);
@@ -0,0 +1,553 @@
+//! PNG encoding without any dependencies
+//!
+//! Images are written as 8 or 16 bit RGB, whichever fits `depth`, with colors scaled up to the
+//! full range of the bit depth. Every row is filtered with whichever PNG filter makes it smallest
+//! and the rows are compressed with deflate, using fixed Huffman codes and LZ77 matches, or
+//! stored as they are if that turns out smaller.
+
+use std::io::{self, prelude::Write};
+
+use crate::graphics::{utils::create_file, PPMImg};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+impl PPMImg {
+    pub fn write_png(&self, filepath: &str) -> io::Result<()> {
+        self.write_png_to(&mut create_file(filepath)?)
+    }
+
+    /// Write the image to `out` as a PNG
+    pub fn write_png_to(&self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = self.output_size();
+        let (bit_depth, max) = if self.depth < 256 {
+            (8, u8::MAX as f64)
+        } else {
+            (16, u16::MAX as f64)
+        };
+        let bpp = 3 * bit_depth as usize / 8;
+
+        let mut rows = Vec::with_capacity(height as usize * (width as usize * bpp + 1));
+        let (mut prev, mut row) = (vec![0; width as usize * bpp], vec![]);
+        let pixels = self.pixels();
+        for line in pixels.chunks_exact(width.max(1) as usize) {
+            row.clear();
+            for p in line {
+                for &c in [p.red, p.green, p.blue].iter() {
+                    let c = (c as f64 * max / self.depth as f64).round() as u16;
+                    if bit_depth == 8 {
+                        row.push(c as u8);
+                    } else {
+                        row.extend_from_slice(&c.to_be_bytes());
+                    }
+                }
+            }
+            filter_row(&row, &prev, bpp, &mut rows);
+            std::mem::swap(&mut prev, &mut row);
+        }
+
+        let mut ihdr = vec![];
+        ihdr.extend_from_slice(&width.to_be_bytes());
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        // bit depth, RGB, deflate, adaptive filtering, no interlacing
+        ihdr.extend_from_slice(&[bit_depth, 2, 0, 0, 0]);
+
+        out.write_all(&SIGNATURE)?;
+        write_chunk(out, b"IHDR", &ihdr)?;
+        write_chunk(out, b"IDAT", &zlib(&rows))?;
+        write_chunk(out, b"IEND", &[])?;
+        out.flush()
+    }
+}
+
+/// Write a chunk with its length and CRC
+fn write_chunk(out: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let crc = crc32_update(crc32_update(0xffff_ffff, kind), data) ^ 0xffff_ffff;
+    out.write_all(&crc.to_be_bytes())
+}
+
+//----------------------------------------- checksums
+
+/// Continue a CRC-32 (as used by PNG and zlib's gzip) over `data`.
+/// Start with 0xffffffff and xor the result with 0xffffffff at the end.
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0xffff_ffff, data) ^ 0xffff_ffff
+}
+
+pub(crate) fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 bytes is the most that can be summed up before b overflows
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+//----------------------------------------- filtering
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Append `row` to `out`, filtered with the filter type whose output has the smallest sum of
+/// absolute values (as signed bytes), after a byte with the filter type.
+fn filter_row(row: &[u8], prev: &[u8], bpp: usize, out: &mut Vec<u8>) {
+    let mut best: Option<(u64, u8, Vec<u8>)> = None;
+    for kind in 0..5u8 {
+        let filtered: Vec<u8> = (0..row.len())
+            .map(|i| {
+                let a = if i >= bpp { row[i - bpp] } else { 0 };
+                let b = prev[i];
+                let c = if i >= bpp { prev[i - bpp] } else { 0 };
+                let predicted = match kind {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) / 2) as u8,
+                    _ => paeth(a, b, c),
+                };
+                row[i].wrapping_sub(predicted)
+            })
+            .collect();
+        let cost = filtered
+            .iter()
+            .map(|&v| (v as i8).unsigned_abs() as u64)
+            .sum();
+        let better = match &best {
+            Some((best_cost, ..)) => cost < *best_cost,
+            None => true,
+        };
+        if better {
+            best = Some((cost, kind, filtered));
+        }
+    }
+    let (_, kind, filtered) = best.unwrap();
+    out.push(kind);
+    out.extend_from_slice(&filtered);
+}
+
+//----------------------------------------- deflate
+
+/// Bits are packed starting from the least significant bit of each byte
+struct BitWriter {
+    out: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn new(out: Vec<u8>) -> Self {
+        BitWriter {
+            out,
+            buffer: 0,
+            bits: 0,
+        }
+    }
+
+    /// Write the `n` lowest bits of `value`, least significant first
+    fn write_bits(&mut self, value: u32, n: u32) {
+        self.buffer |= value << self.bits;
+        self.bits += n;
+        while self.bits >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    /// Write a Huffman code of `len` bits, most significant first
+    fn write_code(&mut self, code: u32, len: u32) {
+        self.write_bits(code.reverse_bits() >> (32 - len), len);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.out.push(self.buffer as u8);
+        }
+        self.out
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+const WINDOW: usize = 1 << 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// How many earlier positions with the same hash are tried for each match
+const MAX_CHAIN: usize = 32;
+
+/// Write a literal/length symbol with the fixed Huffman code
+fn write_fixed_literal(w: &mut BitWriter, symbol: u32) {
+    match symbol {
+        0..=143 => w.write_code(0x30 + symbol, 8),
+        144..=255 => w.write_code(0x190 + symbol - 144, 9),
+        256..=279 => w.write_code(symbol - 256, 7),
+        _ => w.write_code(0xc0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(w: &mut BitWriter, len: usize, dist: usize) {
+    let i = LENGTH_BASE.partition_point(|&b| b as usize <= len) - 1;
+    write_fixed_literal(w, 257 + i as u32);
+    w.write_bits(
+        (len - LENGTH_BASE[i] as usize) as u32,
+        LENGTH_EXTRA[i] as u32,
+    );
+    let i = DIST_BASE.partition_point(|&b| b as usize <= dist) - 1;
+    w.write_code(i as u32, 5);
+    w.write_bits((dist - DIST_BASE[i] as usize) as u32, DIST_EXTRA[i] as u32);
+}
+
+/// Compress `data` into a single deflate block with fixed Huffman codes,
+/// finding repeats with a hash chain over the last 32K bytes
+fn deflate_fixed(data: &[u8]) -> Vec<u8> {
+    let hash = |i: usize| {
+        ((data[i] as usize) << 10 ^ (data[i + 1] as usize) << 5 ^ data[i + 2] as usize)
+            & (WINDOW - 1)
+    };
+    // most recent position + 1 with each hash, and the previous one with the same hash
+    let mut head = vec![0usize; WINDOW];
+    let mut prev = vec![0usize; WINDOW];
+    let insert = |head: &mut [usize], prev: &mut [usize], i: usize| {
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(i);
+            prev[i % WINDOW] = head[h];
+            head[h] = i + 1;
+        }
+    };
+
+    let mut w = BitWriter::new(vec![]);
+    // last block, fixed Huffman codes
+    w.write_bits(1, 1);
+    w.write_bits(1, 2);
+
+    let mut i = 0;
+    while i < data.len() {
+        let (mut best_len, mut best_dist) = (0, 0);
+        if i + MIN_MATCH <= data.len() {
+            let mut candidate = head[hash(i)];
+            let mut chain = 0;
+            while candidate > 0 && chain < MAX_CHAIN {
+                let j = candidate - 1;
+                if i - j > WINDOW - 1 {
+                    break;
+                }
+                let max = MAX_MATCH.min(data.len() - i);
+                let len = (0..max).take_while(|&k| data[j + k] == data[i + k]).count();
+                if len > best_len {
+                    best_len = len;
+                    best_dist = i - j;
+                    if len == max {
+                        break;
+                    }
+                }
+                candidate = prev[j % WINDOW];
+                chain += 1;
+            }
+        }
+
+        if best_len >= MIN_MATCH {
+            write_match(&mut w, best_len, best_dist);
+            for k in i..i + best_len {
+                insert(&mut head, &mut prev, k);
+            }
+            i += best_len;
+        } else {
+            write_fixed_literal(&mut w, data[i] as u32);
+            insert(&mut head, &mut prev, i);
+            i += 1;
+        }
+    }
+    // end of block
+    write_fixed_literal(&mut w, 256);
+    w.finish()
+}
+
+/// `data` in deflate blocks without compression
+fn deflate_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        // an empty last block
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        // header bits, padded to a byte
+        out.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out
+}
+
+/// `data` as a zlib stream, compressed if that makes it smaller
+pub(crate) fn zlib(data: &[u8]) -> Vec<u8> {
+    let fixed = deflate_fixed(data);
+    let stored = deflate_stored(data);
+    // deflate with a 32K window, no preset dictionary, header checksum
+    let mut out = vec![0x78, 0x01];
+    out.extend_from_slice(if fixed.len() < stored.len() {
+        &fixed
+    } else {
+        &stored
+    });
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{Canvas, RGB};
+
+    struct BitReader<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl BitReader<'_> {
+        /// Read `n` bits, least significant first
+        fn bits(&mut self, n: u32) -> u32 {
+            let mut v = 0;
+            for k in 0..n {
+                v |= ((self.data[self.pos / 8] >> (self.pos % 8)) as u32 & 1) << k;
+                self.pos += 1;
+            }
+            v
+        }
+
+        /// Read a fixed Huffman literal/length symbol, most significant bit first
+        fn fixed_literal(&mut self) -> u32 {
+            let (mut code, mut len) = (0, 0);
+            loop {
+                code = code << 1 | self.bits(1);
+                len += 1;
+                match (len, code) {
+                    (7, 0..=23) => return code + 256,
+                    (8, 0x30..=0xbf) => return code - 0x30,
+                    (8, 0xc0..=0xc7) => return code - 0xc0 + 280,
+                    (9, 0x190..=0x1ff) => return code - 0x190 + 144,
+                    _ => assert!(len < 9, "Invalid code"),
+                }
+            }
+        }
+    }
+
+    /// Just enough of inflate to read back stored and fixed Huffman blocks
+    fn inflate(data: &[u8]) -> Vec<u8> {
+        let mut r = BitReader { data, pos: 0 };
+        let mut out: Vec<u8> = vec![];
+        loop {
+            let last = r.bits(1);
+            match r.bits(2) {
+                0 => {
+                    // skip to the next byte
+                    r.pos = r.pos.div_ceil(8) * 8;
+                    let len = r.bits(16);
+                    assert_eq!(len ^ 0xffff, r.bits(16));
+                    for _ in 0..len {
+                        out.push(r.bits(8) as u8);
+                    }
+                }
+                1 => loop {
+                    match r.fixed_literal() {
+                        symbol @ 0..=255 => out.push(symbol as u8),
+                        256 => break,
+                        symbol => {
+                            let i = symbol as usize - 257;
+                            let len =
+                                LENGTH_BASE[i] as usize + r.bits(LENGTH_EXTRA[i] as u32) as usize;
+                            let d = (r.bits(5).reverse_bits() >> 27) as usize;
+                            let dist =
+                                DIST_BASE[d] as usize + r.bits(DIST_EXTRA[d] as u32) as usize;
+                            for _ in 0..len {
+                                out.push(out[out.len() - dist]);
+                            }
+                        }
+                    }
+                },
+                _ => panic!("Dynamic Huffman blocks are never written"),
+            }
+            if last == 1 {
+                return out;
+            }
+        }
+    }
+
+    fn unzlib(data: &[u8]) -> Vec<u8> {
+        assert_eq!(0, (data[0] as u32 * 256 + data[1] as u32) % 31);
+        let out = inflate(&data[2..data.len() - 4]);
+        assert_eq!(&adler32(&out).to_be_bytes(), &data[data.len() - 4..]);
+        out
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+        assert_eq!(0x11e6_0398, adler32(b"Wikipedia"));
+        let long = vec![0xff; 100_000];
+        assert_eq!(0x149a_302c, adler32(&long));
+    }
+
+    #[test]
+    fn test_zlib_round_trip() {
+        let text = b"Shadows and gradients, gradients and shadows, shadows and shadows".to_vec();
+        let mut noise = vec![];
+        let mut x = 12345u32;
+        for _ in 0..70_000 {
+            x = x.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            noise.push((x >> 16) as u8);
+        }
+        let zeros = vec![0; 100_000];
+
+        for data in [vec![], text, noise, zeros].iter() {
+            assert_eq!(data, &unzlib(&zlib(data)));
+        }
+        assert!(zlib(&[0; 100_000]).len() < 1000);
+        assert!(deflate_stored(&[0; 100_000]).len() > 100_000);
+    }
+
+    /// Decode a png written by `write_png_to` back into (width, height, bit depth, samples)
+    fn decode(png: &[u8]) -> (u32, u32, u8, Vec<u16>) {
+        assert_eq!(&SIGNATURE, &png[..8]);
+        let mut pos = 8;
+        let (mut header, mut idat) = (vec![], vec![]);
+        loop {
+            let len = u32::from_be_bytes([png[pos], png[pos + 1], png[pos + 2], png[pos + 3]]);
+            let body = &png[pos + 4..pos + 8 + len as usize];
+            let crc = &png[pos + 8 + len as usize..pos + 12 + len as usize];
+            assert_eq!(&crc32(body).to_be_bytes(), crc);
+            pos += 12 + len as usize;
+            match &body[..4] {
+                b"IHDR" => header = body[4..].to_vec(),
+                b"IDAT" => idat.extend_from_slice(&body[4..]),
+                b"IEND" => break,
+                kind => panic!("Unexpected chunk {:?}", kind),
+            }
+        }
+        let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        let bit_depth = header[8];
+        assert_eq!(&[2, 0, 0, 0], &header[9..]);
+
+        let bpp = 3 * bit_depth as usize / 8;
+        let stride = width as usize * bpp;
+        let data = unzlib(&idat);
+        let mut prev = vec![0u8; stride];
+        let mut bytes = vec![];
+        for line in data.chunks_exact(stride + 1) {
+            let mut row = line[1..].to_vec();
+            for i in 0..stride {
+                let a = if i >= bpp { row[i - bpp] } else { 0 };
+                let (b, c) = (prev[i], if i >= bpp { prev[i - bpp] } else { 0 });
+                row[i] = row[i].wrapping_add(match line[0] {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) / 2) as u8,
+                    _ => paeth(a, b, c),
+                });
+            }
+            bytes.extend_from_slice(&row);
+            prev = row;
+        }
+        let samples = if bit_depth == 8 {
+            bytes.iter().map(|&b| b as u16).collect()
+        } else {
+            bytes
+                .chunks_exact(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                .collect()
+        };
+        (width, height, bit_depth, samples)
+    }
+
+    fn encode(img: &PPMImg) -> Vec<u8> {
+        let mut out = vec![];
+        img.write_png_to(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_png_8_bit() {
+        let mut img = PPMImg::new(20, 30, 100);
+        img.set_fg_color(RGB::new(100, 50, 0));
+        img.fill_circle(15, 10, 6);
+        img.set_fg_color(RGB::new(0, 20, 100));
+        img.draw_line(0., 0., 0., 19.);
+
+        let (width, height, bit_depth, samples) = decode(&encode(&img));
+        assert_eq!((30, 20, 8), (width, height, bit_depth));
+        // scaled from a depth of 100 to 255
+        let at = |x: usize, y: usize| {
+            let i = (y * 30 + x) * 3;
+            (samples[i], samples[i + 1], samples[i + 2])
+        };
+        assert_eq!((255, 128, 0), at(15, 10));
+        assert_eq!((0, 51, 255), at(0, 0));
+        assert_eq!((0, 0, 0), at(29, 0));
+    }
+
+    #[test]
+    fn test_png_16_bit() {
+        let mut img = PPMImg::new_supersampled(3, 4, 1000, 2, crate::graphics::Filter::Box);
+        img.set_fg_color(RGB::new(1000, 0, 500));
+        // every sample of the pixel
+        img.plot(0, 0);
+
+        let (width, height, bit_depth, samples) = decode(&encode(&img));
+        // written at the output size
+        assert_eq!((4, 3, 16), (width, height, bit_depth));
+        assert_eq!(&[65535, 0, 32768], &samples[..3]);
+        assert!(samples[3..].iter().all(|&s| s == 0));
+    }
+}
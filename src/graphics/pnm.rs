@@ -0,0 +1,221 @@
+//! Single channel output, as grayscale PGM (P2 and P5) and black and white PBM (P1 and P4)
+//!
+//! Colors are turned into gray levels by their luminance, and gray levels into black and
+//! white by a `Halftone`.
+
+use std::io::{self, prelude::Write};
+
+use crate::graphics::{utils::create_file, PPMImg};
+
+/// How gray levels are turned into black and white
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Halftone {
+    /// Black below this gray level, white at or above it
+    Threshold(u16),
+    /// Black or white at half the depth, with the error spread over the pixels to the right
+    /// and below (Floyd-Steinberg), so areas keep their average gray level
+    Dither,
+}
+
+impl PPMImg {
+    /// Gray level of every pixel of the output image, row by row, from 0 to `depth`
+    pub fn grayscale(&self) -> Vec<u16> {
+        self.pixels().into_iter().map(|c| c.luminance()).collect()
+    }
+
+    /// Every pixel of the output image, row by row, as true for black and false for white
+    pub fn bitmap(&self, halftone: Halftone) -> Vec<bool> {
+        let gray = self.grayscale();
+        match halftone {
+            Halftone::Threshold(level) => gray.iter().map(|&g| g < level).collect(),
+            Halftone::Dither => {
+                let (width, height) = self.output_size();
+                let (width, height) = (width as usize, height as usize);
+                let depth = self.depth as f64;
+                let mut levels: Vec<f64> = gray.iter().map(|&g| g as f64).collect();
+                let mut black = vec![false; levels.len()];
+                for y in 0..height {
+                    for x in 0..width {
+                        let i = y * width + x;
+                        black[i] = levels[i] < depth / 2.;
+                        let error = levels[i] - if black[i] { 0. } else { depth };
+                        if x + 1 < width {
+                            levels[i + 1] += error * 7. / 16.;
+                        }
+                        if y + 1 < height {
+                            if x > 0 {
+                                levels[i + width - 1] += error * 3. / 16.;
+                            }
+                            levels[i + width] += error * 5. / 16.;
+                            if x + 1 < width {
+                                levels[i + width + 1] += error / 16.;
+                            }
+                        }
+                    }
+                }
+                black
+            }
+        }
+    }
+
+    pub fn write_pgm_binary(&self, filepath: &str) -> io::Result<()> {
+        self.write_pgm_binary_to(&mut create_file(filepath)?)
+    }
+
+    /// Write the gray levels of the image to `out` as a binary (P5) PGM,
+    /// with two bytes per sample if `depth` doesn't fit in one
+    pub fn write_pgm_binary_to(&self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = self.output_size();
+        writeln!(out, "P5")?;
+        writeln!(out, "{} {} {}", width, height, self.depth)?;
+        let mut bytes = Vec::with_capacity((width * height) as usize * 2);
+        for g in self.grayscale() {
+            if self.depth < 256 {
+                bytes.push(g as u8);
+            } else {
+                bytes.extend_from_slice(&g.to_be_bytes());
+            }
+        }
+        out.write_all(&bytes)?;
+        out.flush()
+    }
+
+    pub fn write_pgm_ascii(&self, filepath: &str) -> io::Result<()> {
+        self.write_pgm_ascii_to(&mut create_file(filepath)?)
+    }
+
+    /// Write the gray levels of the image to `out` as a plain (P2) PGM, one pixel per line
+    pub fn write_pgm_ascii_to(&self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = self.output_size();
+        writeln!(out, "P2")?;
+        writeln!(out, "{} {} {}", width, height, self.depth)?;
+        for g in self.grayscale() {
+            writeln!(out, "{}", g)?;
+        }
+        out.flush()
+    }
+
+    pub fn write_pbm_binary(&self, filepath: &str, halftone: Halftone) -> io::Result<()> {
+        self.write_pbm_binary_to(&mut create_file(filepath)?, halftone)
+    }
+
+    /// Write the image to `out` in black and white as a binary (P4) PBM,
+    /// with eight pixels per byte and every row starting on a new byte
+    pub fn write_pbm_binary_to(&self, out: &mut impl Write, halftone: Halftone) -> io::Result<()> {
+        let (width, height) = self.output_size();
+        writeln!(out, "P4")?;
+        writeln!(out, "{} {}", width, height)?;
+        let mut bytes = vec![];
+        if width > 0 {
+            for row in self.bitmap(halftone).chunks(width as usize) {
+                for pixels in row.chunks(8) {
+                    let byte = pixels
+                        .iter()
+                        .enumerate()
+                        .fold(0u8, |byte, (i, &black)| byte | (black as u8) << (7 - i));
+                    bytes.push(byte);
+                }
+            }
+        }
+        out.write_all(&bytes)?;
+        out.flush()
+    }
+
+    pub fn write_pbm_ascii(&self, filepath: &str, halftone: Halftone) -> io::Result<()> {
+        self.write_pbm_ascii_to(&mut create_file(filepath)?, halftone)
+    }
+
+    /// Write the image to `out` in black and white as a plain (P1) PBM,
+    /// with 1 for black and 0 for white and at most 35 pixels per line
+    pub fn write_pbm_ascii_to(&self, out: &mut impl Write, halftone: Halftone) -> io::Result<()> {
+        let (width, height) = self.output_size();
+        writeln!(out, "P1")?;
+        writeln!(out, "{} {}", width, height)?;
+        if width > 0 {
+            for row in self.bitmap(halftone).chunks(width as usize) {
+                // lines of plain PBMs should be no longer than 70 characters
+                for pixels in row.chunks(35) {
+                    let line: Vec<&str> = pixels
+                        .iter()
+                        .map(|&black| if black { "1" } else { "0" })
+                        .collect();
+                    writeln!(out, "{}", line.join(" "))?;
+                }
+            }
+        }
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{canvas::FillRule, test_utils::gray_row, Canvas, RGB};
+
+    #[test]
+    fn test_grayscale() {
+        assert_eq!(76, RGB::new(255, 0, 0).luminance());
+        assert_eq!(150, RGB::new(0, 255, 0).luminance());
+        assert_eq!(29, RGB::new(0, 0, 255).luminance());
+        assert_eq!(200, RGB::gray(200).luminance());
+
+        let mut img = PPMImg::new(1, 2, 255);
+        img.set_fg_color(RGB::new(0, 255, 0));
+        img.plot(1, 0);
+        let mut out = vec![];
+        img.write_pgm_binary_to(&mut out).unwrap();
+        assert_eq!(b"P5\n2 1 255\n\0\x96".to_vec(), out);
+
+        let mut out = vec![];
+        img.write_pgm_ascii_to(&mut out).unwrap();
+        assert_eq!("P2\n2 1 255\n0\n150\n", String::from_utf8(out).unwrap());
+
+        let img = gray_row(&[1000, 258], 1000);
+        let mut out = vec![];
+        img.write_pgm_binary_to(&mut out).unwrap();
+        assert_eq!(b"P5\n2 1 1000\n\x03\xe8\x01\x02".to_vec(), out);
+    }
+
+    #[test]
+    fn test_pbm() {
+        // 10 pixels take two bytes, padded with zeroes
+        let img = gray_row(&[0, 255, 0, 0, 255, 255, 127, 128, 0, 200], 255);
+        let threshold = Halftone::Threshold(128);
+        let mut out = vec![];
+        img.write_pbm_binary_to(&mut out, threshold).unwrap();
+        assert_eq!(b"P4\n10 1\n\xb2\x80".to_vec(), out);
+
+        let mut out = vec![];
+        img.write_pbm_ascii_to(&mut out, threshold).unwrap();
+        assert_eq!(
+            "P1\n10 1\n1 0 1 1 0 0 1 0 1 0\n",
+            String::from_utf8(out).unwrap()
+        );
+
+        // long rows are split over several lines
+        let img = PPMImg::new(2, 40, 255);
+        let mut out = vec![];
+        img.write_pbm_ascii_to(&mut out, threshold).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(6, out.lines().count());
+        assert!(out.lines().all(|line| line.len() <= 70));
+    }
+
+    #[test]
+    fn test_dither() {
+        // a quarter gray is about a quarter white, some error is lost off the edges
+        let mut img = PPMImg::new(16, 16, 100);
+        img.set_fg_color(RGB::gray(25));
+        img.fill_polygon(
+            &[vec![(-1., -1.), (16., -1.), (16., 16.), (-1., 16.)]],
+            FillRule::NonZero,
+        );
+        let bitmap = img.bitmap(Halftone::Dither);
+        let white = bitmap.iter().filter(|&&black| !black).count();
+        assert!((white as i32 - 64).abs() <= 6, "{} white pixels", white);
+
+        // black and white stay as they are
+        let img = gray_row(&[0, 100, 0, 100], 100);
+        assert_eq!(vec![true, false, true, false], img.bitmap(Halftone::Dither));
+    }
+}
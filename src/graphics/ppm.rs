@@ -0,0 +1,218 @@
+//! Reading PPM images, both plain (P3) and raw (P6)
+//!
+//! Comments are allowed anywhere whitespace is in the header, and in between the samples of a
+//! plain PPM. Samples take two bytes, most significant first, in a raw PPM with a maxval above
+//! 255.
+
+use std::{
+    fs::File,
+    io::{self, prelude::Read, BufReader},
+};
+
+use crate::graphics::{PPMImg, RGB, RGBA};
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Goes through the bytes of a PPM
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    /// Skip whitespace and comments
+    fn skip_whitespace(&mut self) {
+        while let Some(&c) = self.data.get(self.pos) {
+            if c == b'#' {
+                // up to the end of the line
+                while !matches!(self.data.get(self.pos), None | Some(b'\n') | Some(b'\r')) {
+                    self.pos += 1;
+                }
+            } else if c.is_ascii_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// The next number in ascii, called `what` in errors
+    fn number(&mut self, what: &str) -> io::Result<u32> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.data.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(match self.data.get(self.pos) {
+                Some(&c) => invalid(format!(
+                    "Expected {} at byte {}, found {:?}",
+                    what, start, c as char
+                )),
+                None => invalid(format!("Unexpected end of file, expected {}", what)),
+            });
+        }
+        std::str::from_utf8(&self.data[start..self.pos])
+            .unwrap()
+            .parse()
+            .map_err(|_| invalid(format!("{} at byte {} is too large", what, start)))
+    }
+}
+
+impl PPMImg {
+    /// Read a P3 or P6 image from the file at `filepath`
+    pub fn read_ppm(filepath: &str) -> io::Result<PPMImg> {
+        Self::read_ppm_from(&mut BufReader::new(File::open(filepath)?))
+    }
+
+    /// Read a P3 or P6 image from `reader`.
+    ///
+    /// The image has the size of the file and its maxval as depth, with every pixel drawn.
+    pub fn read_ppm_from(reader: &mut impl Read) -> io::Result<PPMImg> {
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+        let mut p = Parser {
+            data: &data,
+            pos: 0,
+        };
+
+        let raw = match data.get(..2) {
+            Some(b"P3") => false,
+            Some(b"P6") => true,
+            _ => return Err(invalid("Not a PPM, expected P3 or P6".to_owned())),
+        };
+        p.pos = 2;
+        let width = p.number("width")?;
+        let height = p.number("height")?;
+        let maxval = p.number("maxval")?;
+        if maxval == 0 || maxval > u16::MAX as u32 {
+            let msg = format!("maxval must be in 1..=65535, not {}", maxval);
+            return Err(invalid(msg));
+        }
+        let len = width as usize * height as usize;
+
+        let samples: Vec<u32> = if raw {
+            // exactly one whitespace character before the raster
+            match data.get(p.pos) {
+                Some(c) if c.is_ascii_whitespace() => p.pos += 1,
+                _ => return Err(invalid("Expected whitespace after maxval".to_owned())),
+            }
+            let size = if maxval < 256 { 1 } else { 2 };
+            let raster = &data[p.pos..];
+            if raster.len() < len * 3 * size {
+                return Err(invalid(format!(
+                    "Unexpected end of file, expected {} bytes of pixels but found {}",
+                    len * 3 * size,
+                    raster.len()
+                )));
+            }
+            raster
+                .chunks_exact(size)
+                .take(len * 3)
+                .map(|s| s.iter().fold(0, |v, &b| v << 8 | b as u32))
+                .collect()
+        } else {
+            (0..len * 3)
+                .map(|_| p.number("sample"))
+                .collect::<io::Result<_>>()?
+        };
+
+        if let Some(i) = samples.iter().position(|&s| s > maxval) {
+            return Err(invalid(format!(
+                "Sample {} of pixel {} is {}, more than maxval {}",
+                i % 3,
+                i / 3,
+                samples[i],
+                maxval
+            )));
+        }
+
+        let depth = maxval as u16;
+        let mut img = PPMImg::new(height, width, depth);
+        for (pixel, s) in img.data.iter_mut().zip(samples.chunks_exact(3)) {
+            let color = RGB::new(s[0] as u16, s[1] as u16, s[2] as u16);
+            *pixel = RGBA::from_rgb(color, depth);
+        }
+        Ok(img)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::Canvas;
+
+    fn read(data: &[u8]) -> io::Result<PPMImg> {
+        PPMImg::read_ppm_from(&mut &data[..])
+    }
+
+    fn error(data: &[u8]) -> String {
+        let err = read(data).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        err.to_string()
+    }
+
+    #[test]
+    fn test_read_plain() {
+        let img = read(b"P3\n# a comment\n2 # another\n\t 1\n100\n100 0 50   0\n0#\n 1\n").unwrap();
+        assert_eq!((2, 1), img.output_size());
+        assert_eq!(100, img.depth());
+        assert_eq!(Some(RGB::new(100, 0, 50)), img.get_pixel(0, 0));
+        assert_eq!(Some(RGB::new(0, 0, 1)), img.get_pixel(1, 0));
+    }
+
+    #[test]
+    fn test_read_raw() {
+        let mut data = b"P6 1 2 255\n".to_vec();
+        data.extend_from_slice(&[1, 2, 3, 255, b'\n', 0]);
+        let img = read(&data).unwrap();
+        assert_eq!((1, 2), img.output_size());
+        assert_eq!(Some(RGB::new(1, 2, 3)), img.get_pixel(0, 0));
+        assert_eq!(Some(RGB::new(255, 10, 0)), img.get_pixel(0, 1));
+
+        // 16 bit samples
+        let mut data = b"P6\n1 1\n# big\n1000 ".to_vec();
+        data.extend_from_slice(&[0x03, 0xe8, 0x01, 0xf4, 0, 7]);
+        let img = read(&data).unwrap();
+        assert_eq!(1000, img.depth());
+        assert_eq!(Some(RGB::new(1000, 500, 7)), img.get_pixel(0, 0));
+    }
+
+    #[test]
+    fn test_read_errors() {
+        assert!(error(b"P5 1 1 255\n\0").contains("expected P3 or P6"));
+        assert!(error(b"P3 1 x 255").contains("Expected height at byte 5"));
+        assert!(error(b"P3 1 1").contains("end of file, expected maxval"));
+        assert!(error(b"P3 1 1 0 0 0 0").contains("maxval"));
+        assert!(error(b"P3 1 1 70000 0 0 0").contains("maxval"));
+        assert!(error(b"P3 2 1 9 1 2 3 4 5").contains("expected sample"));
+        assert!(error(b"P3 1 1 9 1 20 3").contains("Sample 1 of pixel 0 is 20"));
+        assert!(error(b"P6 2 1 255\n\0\0\0").contains("expected 6 bytes of pixels but found 3"));
+        assert!(error(b"P3 99999999999 1 1").contains("width at byte 3 is too large"));
+
+        let missing = PPMImg::read_ppm("this/file/does/not/exist.ppm").unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, missing.kind());
+    }
+
+    #[test]
+    fn test_read_written() {
+        let mut img = PPMImg::new(20, 30, 1000);
+        img.set_fg_color(RGB::new(1000, 300, 0));
+        img.fill_circle(15, 10, 8);
+        img.set_fg_color(RGB::new(7, 600, 999));
+        img.draw_line(0., 0., 29., 19.);
+
+        let (mut ascii, mut binary) = (vec![], vec![]);
+        img.write_ascii_to(&mut ascii).unwrap();
+        img.write_binary_to(&mut binary).unwrap();
+
+        for out in [ascii, binary].iter() {
+            let read = PPMImg::read_ppm_from(&mut &out[..]).unwrap();
+            assert_eq!(img.output_size(), read.output_size());
+            assert_eq!(img.depth(), read.depth());
+            assert!(img.pixels() == read.pixels());
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Downsampling of supersampled images
+
+use crate::graphics::RGB;
+
+/// Filter used to combine the samples of a supersampled image into one pixel
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// Average of the n by n samples inside the pixel
+    Box,
+    /// Weighted average of the samples within one pixel of the pixel center,
+    /// falling off linearly with distance. Smoother than `Box`.
+    Tent,
+}
+
+/// Weights of the samples along one axis for the output pixel at `i`, as (sample index, weight)
+fn weights(i: u32, samples: u32, len: u32, filter: Filter) -> Vec<(u32, f64)> {
+    match filter {
+        Filter::Box => (i * samples..(i + 1) * samples).map(|s| (s, 1.)).collect(),
+        Filter::Tent => {
+            // pixel center and sample centers in sample units
+            let center = (i as f64 + 0.5) * samples as f64;
+            let start = (i * samples).saturating_sub(samples);
+            let end = ((i + 2) * samples).min(len);
+            (start..end)
+                .map(|s| {
+                    let dist = (s as f64 + 0.5 - center).abs();
+                    (s, (1. - dist / samples as f64).max(0.))
+                })
+                .filter(|&(_, w)| w > 0.)
+                .collect()
+        }
+    }
+}
+
+/// Downsample an image of `width` by `height` samples by `samples` in both directions
+///
+/// Returns the (width / samples) by (height / samples) filtered image, row by row
+pub(crate) fn downsample(
+    data: &[RGB],
+    width: u32,
+    height: u32,
+    samples: u32,
+    filter: Filter,
+) -> Vec<RGB> {
+    if samples == 1 {
+        return data.to_vec();
+    }
+    let (out_width, out_height) = (width / samples, height / samples);
+    let xweights: Vec<_> = (0..out_width)
+        .map(|x| weights(x, samples, width, filter))
+        .collect();
+
+    let mut out = Vec::with_capacity((out_width * out_height) as usize);
+    for y in 0..out_height {
+        let yweights = weights(y, samples, height, filter);
+        for xw in xweights.iter() {
+            let (mut r, mut g, mut b, mut total) = (0., 0., 0., 0.);
+            for &(sy, wy) in yweights.iter() {
+                for &(sx, wx) in xw.iter() {
+                    let w = wx * wy;
+                    let color = data[(sy * width + sx) as usize];
+                    r += color.red as f64 * w;
+                    g += color.green as f64 * w;
+                    b += color.blue as f64 * w;
+                    total += w;
+                }
+            }
+            let avg = |c: f64| (c / total).round() as u16;
+            out.push(RGB::new(avg(r), avg(g), avg(b)));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downsample_box() {
+        // 4 by 2 samples into 2 by 1 pixels
+        let (w, b) = (RGB::gray(255), RGB::gray(0));
+        let data = [w, w, w, b, w, w, b, b];
+        let out = downsample(&data, 4, 2, 2, Filter::Box);
+        assert_eq!(vec![w, RGB::gray(64)], out);
+    }
+
+    #[test]
+    fn test_downsample_tent() {
+        // a flat image stays flat
+        let data = vec![RGB::gray(100); 36];
+        assert_eq!(
+            vec![RGB::gray(100); 4],
+            downsample(&data, 6, 6, 3, Filter::Tent)
+        );
+
+        // a single bright sample leaks into the neighboring pixel
+        let mut data = vec![RGB::gray(0); 36];
+        data[2] = RGB::gray(255);
+        let out = downsample(&data, 6, 6, 3, Filter::Tent);
+        assert!(out[0].red > out[1].red && out[1].red > 0);
+        assert_eq!((0, 0), (out[2].red, out[3].red));
+    }
+}
@@ -0,0 +1,248 @@
+//! Shadow maps
+//!
+//! The scene is rendered into a depth buffer from the light's point of view. A point is in
+//! shadow when something else in that depth buffer is nearer to the light than it is.
+
+use crate::graphics::{
+    canvas::scan_triangle,
+    lighting::Light,
+    matrix::{
+        projections::{orthographic, perspective},
+        transform::look_at,
+        Matrix,
+    },
+    utils::mapper,
+    vector::Vec3,
+};
+
+/// How shadow maps are made and sampled
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShadowSettings {
+    /// width and height of the depth map in texels
+    pub size: u32,
+    /// distance points are moved towards the light before testing, to keep surfaces
+    /// from shadowing themselves (shadow acne)
+    pub bias: f64,
+    /// percentage-closer filtering radius in texels. Each lookup averages
+    /// (2 * pcf + 1)^2 depth tests, 0 for hard shadows
+    pub pcf: u32,
+}
+
+impl ShadowSettings {
+    pub fn new(size: u32, bias: f64, pcf: u32) -> Self {
+        ShadowSettings { size, bias, pcf }
+    }
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings::new(512, 1., 1)
+    }
+}
+
+/// Depth of a scene as seen from a light
+#[derive(Clone, Debug)]
+pub struct ShadowMap {
+    /// from the space the scene was given in to the light's clip space
+    transform: Matrix,
+    settings: ShadowSettings,
+    /// depth at each texel, larger is nearer to the light
+    depth: Vec<f64>,
+}
+
+impl ShadowMap {
+    /// Render the depth map of polygon matrix `scene` as seen from `light`.
+    ///
+    /// Directional lights use an orthographic projection fitted around the scene, spot lights
+    /// a perspective projection covering their cone. Point lights shine in every direction,
+    /// which a single map can't cover, so they get None.
+    pub fn new(light: &Light, scene: &Matrix, settings: ShadowSettings) -> Option<Self> {
+        let (view, spot) = match *light {
+            Light::Directional { direction, .. } => (look_at(Vec3(0., 0., 0.), direction), None),
+            Light::Spot {
+                position,
+                direction,
+                angle,
+                ..
+            } => (look_at(position, direction), Some(angle)),
+            Light::Point { .. } => return None,
+        };
+
+        // bounds of the scene in view space, in front of the light is -z
+        let in_view = scene * &view;
+        let (mut min, mut max) = ([f64::INFINITY; 3], [f64::NEG_INFINITY; 3]);
+        for p in in_view.iter_by_row() {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+        if min[0] > max[0] {
+            // empty scene
+            min = [-1.; 3];
+            max = [1.; 3];
+        }
+
+        let projection = match spot {
+            Some(angle) => {
+                let far = (-min[2]).max(1.) * 1.01;
+                let near = (-max[2]).max(far * 1e-3);
+                // wide cones are cut off, a single map can't look 180 degrees wide
+                perspective((2. * angle).min(170.).to_radians(), 1., near, far)
+            }
+            None => {
+                // a margin so flat scenes don't collapse the volume
+                let m = 1.;
+                orthographic(
+                    min[0] - m,
+                    max[0] + m,
+                    min[1] - m,
+                    max[1] + m,
+                    -max[2] - m,
+                    -min[2] + m,
+                )
+            }
+        };
+        let transform = view * projection;
+
+        let size = settings.size;
+        let mut map = ShadowMap {
+            depth: vec![f64::NEG_INFINITY; (size * size) as usize],
+            transform,
+            settings,
+        };
+
+        let mut projected = (scene * &map.transform).clip_polygons();
+        projected.perspective_divide();
+        projected.ndc_n1to1_to_device(size as f64, size as f64);
+
+        let rows: Vec<&[f64]> = projected.iter_by_row().collect();
+        for tri in rows.chunks_exact(3) {
+            let pts = [
+                (tri[0][0], tri[0][1], tri[0][2]),
+                (tri[1][0], tri[1][1], tri[1][2]),
+                (tri[2][0], tri[2][1], tri[2][2]),
+            ];
+            let depth = &mut map.depth;
+            scan_triangle(pts, [Vec3(0., 0., 0.); 3], |x, y, z, _| {
+                if x >= 0 && y >= 0 && (x as u32) < size && (y as u32) < size {
+                    let d = &mut depth[(y as u32 * size + x as u32) as usize];
+                    *d = d.max(z);
+                }
+            });
+        }
+
+        Some(map)
+    }
+
+    /// How much of the light reaches `point` on a surface with unit normal `normal`,
+    /// from 0 (fully shadowed) to 1 (fully lit).
+    ///
+    /// `to_light` is the unit vector from `point` towards the light. The point is moved along it
+    /// by the bias, more so on surfaces the light only grazes.
+    pub fn visibility(&self, point: Vec3, normal: Vec3, to_light: Vec3) -> f64 {
+        let cos = normal.dot(to_light).max(0.05);
+        let slope = (1. - cos * cos).max(0.).sqrt() / cos;
+        let p = point + to_light * (self.settings.bias * (1. + slope.min(10.)));
+        // p * transform, one column at a time, as done for the whole scene in `new`
+        let clip = |c: usize| {
+            let row = [p.0, p.1, p.2, 1.];
+            self.transform
+                .col_iter(c)
+                .zip(&row)
+                .map(|(t, p)| t * p)
+                .sum::<f64>()
+        };
+        let w = clip(3);
+        if w <= 0. {
+            // behind a spot light
+            return 1.;
+        }
+        let size = self.settings.size;
+        let to_texel = mapper(-1., 1., 0., size as f64);
+        let (x, y, z) = (
+            to_texel(clip(0) / w).round() as i64,
+            to_texel(clip(1) / w).round() as i64,
+            -clip(2) / w,
+        );
+
+        let r = self.settings.pcf as i64;
+        let mut lit = 0;
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let (tx, ty) = (x + dx, y + dy);
+                let inside = tx >= 0 && ty >= 0 && tx < size as i64 && ty < size as i64;
+                // anything outside of the map is lit
+                if !inside || z >= self.depth[(ty * size as i64 + tx) as usize] {
+                    lit += 1;
+                }
+            }
+        }
+        lit as f64 / ((2 * r + 1) * (2 * r + 1)) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::RGB;
+
+    /// A 100 x 100 floor at z = 0 with a box floating above it, x and y in 30..70, z in 20..40
+    fn scene() -> Matrix {
+        let mut m = Matrix::new_polygon_matrix();
+        m.append_polygon((0., 0., 0.), (100., 0., 0.), (100., 100., 0.));
+        m.append_polygon((0., 0., 0.), (100., 100., 0.), (0., 100., 0.));
+        m.add_box((30., 70., 40.), 40., 40., 20.);
+        m
+    }
+
+    #[test]
+    fn test_directional_shadow() {
+        let direction = Vec3(1., 0., -1.);
+        let light = Light::Directional {
+            direction,
+            color: RGB::gray(255),
+        };
+        let map = ShadowMap::new(&light, &scene(), ShadowSettings::new(256, 1., 0)).unwrap();
+        let to_light = (-direction).normalized();
+        let up = Vec3(0., 0., 1.);
+
+        // the box's shadow is pushed towards +x, 20 to 40 units
+        assert_eq!(0., map.visibility(Vec3(85., 50., 0.), up, to_light));
+        assert_eq!(1., map.visibility(Vec3(10., 50., 0.), up, to_light));
+        assert_eq!(1., map.visibility(Vec3(50., 95., 0.), up, to_light));
+        // the top of the box itself is lit
+        assert_eq!(1., map.visibility(Vec3(50., 50., 40.), up, to_light));
+    }
+
+    #[test]
+    fn test_spot_shadow_pcf() {
+        let position = Vec3(50., 50., 100.);
+        let light = Light::Spot {
+            position,
+            direction: Vec3(0., 0., -1.),
+            angle: 45.,
+            color: RGB::gray(255),
+        };
+        let to_light = |p: Vec3| (position - p).normalized();
+        let up = Vec3(0., 0., 1.);
+
+        let hard = ShadowMap::new(&light, &scene(), ShadowSettings::new(256, 1., 0)).unwrap();
+        let under = Vec3(50., 50., 0.);
+        assert_eq!(0., hard.visibility(under, up, to_light(under)));
+        let outside = Vec3(90., 50., 0.);
+        assert_eq!(1., hard.visibility(outside, up, to_light(outside)));
+
+        // the box's shadow edge on the floor is at x = 70 * 100 / 60 ~= 83.3
+        let soft = ShadowMap::new(&light, &scene(), ShadowSettings::new(256, 1., 3)).unwrap();
+        let edge = Vec3(83.3, 50., 0.);
+        let v = soft.visibility(edge, up, to_light(edge));
+        assert!(0. < v && v < 1., "penumbra should be partially lit: {}", v);
+
+        let point = Light::Point {
+            position,
+            color: RGB::gray(255),
+        };
+        assert!(ShadowMap::new(&point, &scene(), ShadowSettings::default()).is_none());
+    }
+}
@@ -0,0 +1,402 @@
+//! A canvas that records what's drawn as SVG elements instead of pixels
+//!
+//! Lines keep their stroke (width, caps and dashes) and triangles become polygons, so the
+//! result stays sharp at any size. There is no depth buffer: elements are painted in the order
+//! they are drawn, and polygon matrices only get back-face culling for hidden surfaces.
+
+use std::io::{self, prelude::Write};
+
+use crate::graphics::{
+    canvas::{Canvas, FillRule, LineCap, Stroke},
+    utils::create_file,
+    RGB,
+};
+
+/// Something recorded on an `SVGImg`
+#[derive(Clone, Debug)]
+enum Element {
+    /// A single pixel from `plot`
+    Pixel {
+        x: i32,
+        y: i32,
+        color: RGB,
+    },
+    Line {
+        from: (f64, f64),
+        to: (f64, f64),
+        color: RGB,
+        stroke: Stroke,
+    },
+    Polygon {
+        points: Vec<(f64, f64)>,
+        color: RGB,
+    },
+    /// Closed rings filled as one shape, from `fill_polygon`
+    Path {
+        rings: Vec<Vec<(f64, f64)>>,
+        rule: FillRule,
+        color: RGB,
+    },
+}
+
+pub struct SVGImg {
+    height: u32,
+    width: u32,
+    depth: u16,
+    /// y goes up from the bottom of the image, like `PPMImg::invert_y`
+    pub invert_y: bool,
+    pub stroke: Stroke,
+    pub fg_color: RGB,
+    pub bg_color: RGB,
+    elements: Vec<Element>,
+}
+
+impl SVGImg {
+    /// Create a new SVGImg, colors on it go up to `depth`
+    /// Default fg color is white, bg_color is black
+    pub fn new(height: u32, width: u32, depth: u16) -> SVGImg {
+        SVGImg {
+            height,
+            width,
+            depth,
+            invert_y: false,
+            stroke: Stroke::default(),
+            fg_color: RGB::gray(depth),
+            bg_color: RGB::gray(0),
+            elements: vec![],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.elements.clear();
+    }
+
+    pub fn write_svg(&self, filepath: &str) -> io::Result<()> {
+        self.write_svg_to(&mut create_file(filepath)?)
+    }
+
+    /// Write the recorded elements to `out` as an svg document
+    pub fn write_svg_to(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = self.width,
+            h = self.height
+        )?;
+        writeln!(
+            out,
+            r#"<rect width="100%" height="100%" fill="{}"/>"#,
+            self.color(self.bg_color)
+        )?;
+
+        for element in self.elements.iter() {
+            match element {
+                Element::Pixel { x, y, color } => {
+                    let (x, y) = self.point(*x as f64, *y as f64);
+                    writeln!(
+                        out,
+                        r#"<rect x="{}" y="{}" width="1" height="1" fill="{}"/>"#,
+                        num(x - 0.5),
+                        num(y - 0.5),
+                        self.color(*color)
+                    )?;
+                }
+                Element::Line {
+                    from,
+                    to,
+                    color,
+                    stroke,
+                } => {
+                    let (from, to) = (self.point(from.0, from.1), self.point(to.0, to.1));
+                    let cap = match stroke.cap {
+                        LineCap::Butt => "butt",
+                        LineCap::Square => "square",
+                        LineCap::Round => "round",
+                    };
+                    write!(
+                        out,
+                        r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="{}" stroke-linecap="{}""#,
+                        num(from.0),
+                        num(from.1),
+                        num(to.0),
+                        num(to.1),
+                        self.color(*color),
+                        num(stroke.width.max(1.)),
+                        cap
+                    )?;
+                    if stroke.is_dashed() {
+                        let dash: Vec<String> = stroke.dash().iter().map(|d| num(*d)).collect();
+                        write!(
+                            out,
+                            r#" stroke-dasharray="{}" stroke-dashoffset="{}""#,
+                            dash.join(" "),
+                            num(stroke.dash_offset)
+                        )?;
+                    }
+                    writeln!(out, "/>")?;
+                }
+                Element::Polygon { points, color } => {
+                    let points: Vec<String> = points
+                        .iter()
+                        .map(|&(x, y)| {
+                            let (x, y) = self.point(x, y);
+                            format!("{},{}", num(x), num(y))
+                        })
+                        .collect();
+                    writeln!(
+                        out,
+                        r#"<polygon points="{}" fill="{}"/>"#,
+                        points.join(" "),
+                        self.color(*color)
+                    )?;
+                }
+                Element::Path { rings, rule, color } => {
+                    let mut d = vec![];
+                    for ring in rings.iter().filter(|ring| !ring.is_empty()) {
+                        for (i, &(x, y)) in ring.iter().enumerate() {
+                            let (x, y) = self.point(x, y);
+                            let command = if i == 0 { "M" } else { "L" };
+                            d.push(format!("{}{},{}", command, num(x), num(y)));
+                        }
+                        d.push("Z".to_owned());
+                    }
+                    let rule = match rule {
+                        FillRule::EvenOdd => "evenodd",
+                        FillRule::NonZero => "nonzero",
+                    };
+                    writeln!(
+                        out,
+                        r#"<path d="{}" fill-rule="{}" fill="{}"/>"#,
+                        d.join(" "),
+                        rule,
+                        self.color(*color)
+                    )?;
+                }
+            }
+        }
+
+        writeln!(out, "</svg>")?;
+        out.flush()
+    }
+
+    fn push_line(&mut self, from: (f64, f64), to: (f64, f64), stroke: Stroke) {
+        let color = self.fg_color;
+        self.elements.push(Element::Line {
+            from,
+            to,
+            color,
+            stroke,
+        });
+    }
+
+    /// Position of the center of pixel (x, y) in the svg
+    fn point(&self, x: f64, y: f64) -> (f64, f64) {
+        if self.invert_y {
+            (x + 0.5, self.height as f64 - y - 0.5)
+        } else {
+            (x + 0.5, y + 0.5)
+        }
+    }
+
+    /// `color` as an svg color, scaled from `depth` to 8 bits per channel
+    fn color(&self, color: RGB) -> String {
+        let scale = |c: u16| (c as f64 * 255. / self.depth as f64).round() as u8;
+        format!(
+            "#{:02x}{:02x}{:02x}",
+            scale(color.red),
+            scale(color.green),
+            scale(color.blue)
+        )
+    }
+}
+
+/// A number with at most two decimals and no trailing zeros
+fn num(v: f64) -> String {
+    let s = format!("{:.2}", v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_owned()
+    } else {
+        s.to_owned()
+    }
+}
+
+impl Canvas for SVGImg {
+    fn plot(&mut self, x: i32, y: i32) {
+        let color = self.fg_color;
+        self.elements.push(Element::Pixel { x, y, color });
+    }
+    /// Lines are kept whole, with the current stroke
+    fn draw_line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) {
+        self.push_line((x0, y0), (x1, y1), self.stroke.clone());
+    }
+    fn draw_hairline(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) {
+        self.push_line((x0, y0), (x1, y1), Stroke::default());
+    }
+    /// The line is kept whole, with its dashes starting `phase` pixels into the pattern
+    fn stroke_line(
+        &mut self,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+        stroke: &Stroke,
+        phase: f64,
+    ) -> f64 {
+        let mut dashed = stroke.clone();
+        dashed.dash_offset = phase;
+        self.push_line((x0, y0), (x1, y1), dashed);
+        if !stroke.is_dashed() {
+            return phase;
+        }
+        // like svg, odd patterns are repeated to get an even number of dashes and gaps
+        let repeats = if stroke.dash().len() % 2 == 1 { 2. } else { 1. };
+        let period = stroke.dash().iter().sum::<f64>() * repeats;
+        (phase + (x1 - x0).hypot(y1 - y0)).rem_euclid(period)
+    }
+    fn draw_thick_line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, width: f64, cap: LineCap) {
+        self.push_line((x0, y0), (x1, y1), Stroke::new(width, cap));
+    }
+    /// There is no depth buffer, so this is the same as `draw_line`
+    fn draw_line_z(&mut self, x0: f64, y0: f64, _z0: f64, x1: f64, y1: f64, _z1: f64) {
+        self.draw_line(x0, y0, x1, y1);
+    }
+    fn fill_triangle(&mut self, p0: (f64, f64, f64), p1: (f64, f64, f64), p2: (f64, f64, f64)) {
+        self.elements.push(Element::Polygon {
+            points: vec![(p0.0, p0.1), (p1.0, p1.1), (p2.0, p2.1)],
+            color: self.fg_color,
+        });
+    }
+    /// Rings are kept whole as a path with `rule` as its fill-rule
+    fn fill_polygon(&mut self, rings: &[Vec<(f64, f64)>], rule: FillRule) {
+        self.elements.push(Element::Path {
+            rings: rings.to_vec(),
+            rule,
+            color: self.fg_color,
+        });
+    }
+    /// Nothing is rasterized, so there are no pixels to read back
+    fn get_pixel(&self, _x: i32, _y: i32) -> Option<RGB> {
+        None
+    }
+    fn set_fg_color(&mut self, color: RGB) {
+        self.fg_color = color;
+    }
+    fn set_bg_color(&mut self, color: RGB) {
+        self.bg_color = color;
+    }
+    fn get_fg_color(&self) -> RGB {
+        self.fg_color
+    }
+    fn get_bg_color(&self) -> RGB {
+        self.bg_color
+    }
+    fn set_stroke(&mut self, stroke: Stroke) {
+        self.stroke = stroke;
+    }
+    fn get_stroke(&self) -> &Stroke {
+        &self.stroke
+    }
+    fn width(&self) -> u32 {
+        self.width
+    }
+    fn height(&self) -> u32 {
+        self.height
+    }
+    fn depth(&self) -> u16 {
+        self.depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{canvas::DrawMode, Matrix};
+
+    fn to_string(img: &SVGImg) -> String {
+        let mut out = vec![];
+        img.write_svg_to(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_svg_lines() {
+        let mut img = SVGImg::new(100, 200, 255);
+        img.set_fg_color(RGB::new(255, 0, 0));
+        img.draw_line(0., 0., 10.5, 20.);
+        img.set_stroke(Stroke::new(3., LineCap::Round).dashed(&[4., 2.], 1.));
+        img.draw_line(1., 1., 2., 2.);
+
+        let svg = to_string(&img);
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100""#)
+        );
+        assert!(svg.contains(r##"<rect width="100%" height="100%" fill="#000000"/>"##));
+        assert!(svg.contains(
+            r##"<line x1="0.5" y1="0.5" x2="11" y2="20.5" stroke="#ff0000" stroke-width="1" stroke-linecap="butt"/>"##
+        ));
+        assert!(svg.contains(
+            r#"stroke-width="3" stroke-linecap="round" stroke-dasharray="4 2" stroke-dashoffset="1"/>"#
+        ));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn test_svg_edge_matrix() {
+        let mut img = SVGImg::new(100, 100, 255);
+        let mut m = Matrix::new_edge_matrix();
+        m.append_edge(&[0., 0., 0., 10., 0., 0.]);
+        m.append_edge(&[10., 0., 0., 10., 10., 0.]);
+        img.render_edge_matrix(&m);
+        img.set_stroke(Stroke::new(4., LineCap::Square).dashed(&[3., 1.], 0.));
+        img.render_edge_matrix(&m);
+
+        let svg = to_string(&img);
+        assert!(!svg.contains(r#"width="1" height="1""#), "No pixels");
+        assert_eq!(4, svg.matches("<line").count());
+        assert!(svg.contains(
+            r##"<line x1="0.5" y1="0.5" x2="10.5" y2="0.5" stroke="#ffffff" stroke-width="1" stroke-linecap="butt"/>"##
+        ));
+        assert!(svg.contains(
+            r#"stroke-width="4" stroke-linecap="square" stroke-dasharray="3 1" stroke-dashoffset="0"/>"#
+        ));
+        // the dashes continue around the corner
+        assert!(svg.contains(r#"stroke-dasharray="3 1" stroke-dashoffset="2"/>"#));
+    }
+
+    #[test]
+    fn test_svg_polygons() {
+        let mut img = SVGImg::new(100, 100, 100);
+        img.invert_y = true;
+        img.set_fg_color(RGB::new(100, 50, 0));
+
+        let mut m = Matrix::new_polygon_matrix();
+        m.append_polygon((10., 10., 0.), (90., 10., 0.), (50., 90., 0.));
+        // facing away, culled
+        m.append_polygon((10., 10., 0.), (50., 90., 0.), (90., 10., 0.));
+        img.render_polygon_matrix_with(&m, DrawMode::Filled);
+        img.render_polygon_matrix(&m);
+
+        let svg = to_string(&img);
+        assert_eq!(1, svg.matches("<polygon").count());
+        assert!(
+            svg.contains(r##"<polygon points="10.5,89.5 90.5,89.5 50.5,9.5" fill="#ff8000"/>"##)
+        );
+        assert_eq!(3, svg.matches("<line").count());
+    }
+
+    #[test]
+    fn test_svg_paths() {
+        let mut img = SVGImg::new(100, 100, 255);
+        let mut m = Matrix::new_edge_matrix();
+        m.append_edge(&[0., 0., 0., 10., 0., 0.]);
+        m.append_edge(&[10., 0., 0., 0., 10., 0.]);
+        m.append_edge(&[20., 20., 0., 30., 20., 0.]);
+        img.fill_edge_matrix(&m, FillRule::EvenOdd);
+
+        let svg = to_string(&img);
+        assert!(svg.contains(
+            r##"<path d="M0.5,0.5 L10.5,0.5 L0.5,10.5 Z M20.5,20.5 L30.5,20.5 Z" fill-rule="evenodd" fill="#ffffff"/>"##
+        ));
+    }
+}
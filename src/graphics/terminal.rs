@@ -0,0 +1,116 @@
+//! Drawing a `PPMImg` on a terminal
+//!
+//! Every character cell shows two pixels stacked on top of each other with the upper half
+//! block '▀': the upper pixel is its foreground color and the lower pixel its background color,
+//! both as 24-bit ANSI colors. Images wider than the terminal are scaled down to fit.
+
+use std::{
+    env,
+    io::{self, prelude::Write},
+};
+
+use crate::graphics::PPMImg;
+
+/// Width of the terminal when `$COLUMNS` doesn't say
+const DEFAULT_COLUMNS: u32 = 80;
+
+impl PPMImg {
+    /// Write the image to `out` as ANSI colored half blocks, at most `columns` characters wide
+    pub fn write_terminal(&self, out: &mut impl Write, columns: u32) -> io::Result<()> {
+        let (width, height) = self.output_size();
+        let pixels = self.pixels();
+        // every `scale` by `scale` block of pixels is averaged into one
+        let scale = width.div_ceil(columns.max(1)).max(1);
+        let (cols, rows) = (width.div_ceil(scale), height.div_ceil(scale));
+
+        let block = |bx: u32, by: u32| {
+            let (mut sum, mut count) = ([0u64; 3], 0u64);
+            for y in by * scale..((by + 1) * scale).min(height) {
+                for x in bx * scale..((bx + 1) * scale).min(width) {
+                    let p = pixels[(y * width + x) as usize];
+                    sum[0] += p.red as u64;
+                    sum[1] += p.green as u64;
+                    sum[2] += p.blue as u64;
+                    count += 1;
+                }
+            }
+            let to_8bit = |c: u64| (c as f64 / count as f64 * 255. / self.depth as f64).round();
+            (to_8bit(sum[0]), to_8bit(sum[1]), to_8bit(sum[2]))
+        };
+
+        for row in (0..rows).step_by(2) {
+            for col in 0..cols {
+                let (r, g, b) = block(col, row);
+                write!(out, "\x1b[38;2;{};{};{}m", r, g, b)?;
+                if row + 1 < rows {
+                    let (r, g, b) = block(col, row + 1);
+                    write!(out, "\x1b[48;2;{};{};{}m", r, g, b)?;
+                } else {
+                    // odd number of rows, the lower half is left to the terminal
+                    write!(out, "\x1b[49m")?;
+                }
+                write!(out, "▀")?;
+            }
+            writeln!(out, "\x1b[0m")?;
+        }
+        out.flush()
+    }
+
+    /// Print the image to stdout, as wide as `$COLUMNS` (80 if it isn't set)
+    pub fn print_terminal(&self) -> io::Result<()> {
+        let columns = env::var("COLUMNS")
+            .ok()
+            .and_then(|c| c.parse().ok())
+            .unwrap_or(DEFAULT_COLUMNS);
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        self.write_terminal(&mut out, columns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{Canvas, RGB};
+
+    fn to_string(img: &PPMImg, columns: u32) -> String {
+        let mut out = vec![];
+        img.write_terminal(&mut out, columns).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_half_blocks() {
+        // 2 wide, 3 tall
+        let mut img = PPMImg::new(3, 2, 100);
+        img.set_fg_color(RGB::new(100, 0, 0));
+        img.plot(0, 0);
+        img.set_fg_color(RGB::new(0, 100, 50));
+        img.plot(1, 1);
+        img.plot(0, 2);
+
+        assert_eq!(
+            concat!(
+                "\x1b[38;2;255;0;0m\x1b[48;2;0;0;0m▀",
+                "\x1b[38;2;0;0;0m\x1b[48;2;0;255;128m▀\x1b[0m\n",
+                "\x1b[38;2;0;255;128m\x1b[49m▀",
+                "\x1b[38;2;0;0;0m\x1b[49m▀\x1b[0m\n",
+            ),
+            to_string(&img, 80)
+        );
+    }
+
+    #[test]
+    fn test_scaled_to_fit() {
+        let mut img = PPMImg::new(4, 4, 255);
+        img.set_fg_color(RGB::gray(255));
+        img.plot(0, 0);
+        img.plot(1, 0);
+
+        // each character is a 2 x 2 block of pixels, stacked two high
+        assert_eq!(
+            "\x1b[38;2;128;128;128m\x1b[48;2;0;0;0m▀\x1b[38;2;0;0;0m\x1b[48;2;0;0;0m▀\x1b[0m\n",
+            to_string(&img, 3)
+        );
+    }
+}
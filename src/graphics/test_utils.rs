@@ -0,0 +1,51 @@
+//! Helpers shared by the tests of the drawing and image format modules
+
+use super::{Canvas, PPMImg, RGB};
+
+/// Whether pixel (x, y) of `img` was drawn on, i.e. is not the black background
+pub(crate) fn filled(img: &PPMImg, x: i32, y: i32) -> bool {
+    img.get_pixel(x, y) != Some(RGB::gray(0))
+}
+
+/// Every pixel of `img` that was drawn on as (x, y), row by row.
+///
+/// Panics if they are not all the same color, e.g. when a half transparent fg was plotted
+/// more than once on the same pixel.
+pub(crate) fn lit(img: &PPMImg) -> Vec<(i32, i32)> {
+    let mut points = vec![];
+    let mut first = None;
+    for y in 0..img.height() as i32 {
+        for x in 0..img.width() as i32 {
+            if filled(img, x, y) {
+                let color = img.get_pixel(x, y);
+                let first = *first.get_or_insert(color);
+                assert_eq!(first, color, "({}, {}) is not the same color", x, y);
+                points.push((x, y));
+            }
+        }
+    }
+    points
+}
+
+/// Number of pixels of `img` that are `color`
+pub(crate) fn count(img: &PPMImg, color: RGB) -> usize {
+    let mut n = 0;
+    for y in 0..img.height() as i32 {
+        for x in 0..img.width() as i32 {
+            if img.get_pixel(x, y) == Some(color) {
+                n += 1;
+            }
+        }
+    }
+    n
+}
+
+/// A one pixel high image with a gray level of `levels[x]` at x
+pub(crate) fn gray_row(levels: &[u16], depth: u16) -> PPMImg {
+    let mut img = PPMImg::new(1, levels.len() as u32, depth);
+    for (x, &g) in levels.iter().enumerate() {
+        img.set_fg_color(RGB::gray(g));
+        img.plot(x as i32, 0);
+    }
+    img
+}
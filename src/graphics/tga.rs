@@ -0,0 +1,152 @@
+//! 24 bit TGA output, uncompressed or run length encoded
+//!
+//! Rows are stored from the bottom of the image up, with colors scaled down to 8 bits.
+//! Runs never go past the end of a row.
+
+use std::io::{self, prelude::Write};
+
+use crate::graphics::{utils::create_file, PPMImg};
+
+/// Most pixels a run length packet holds
+const MAX_PACKET: usize = 128;
+
+/// Ends a TGA 2.0 file, without extension or developer areas
+const FOOTER: &[u8] = b"\0\0\0\0\0\0\0\0TRUEVISION-XFILE.\0";
+
+impl PPMImg {
+    pub fn write_tga(&self, filepath: &str, rle: bool) -> io::Result<()> {
+        self.write_tga_to(&mut create_file(filepath)?, rle)
+    }
+
+    /// Write the image to `out` as a 24 bit TGA, with its rows run length encoded if `rle`
+    pub fn write_tga_to(&self, out: &mut impl Write, rle: bool) -> io::Result<()> {
+        let (width, height) = self.output_size();
+        if width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} by {} is too large for a TGA", width, height),
+            ));
+        }
+
+        let mut header = vec![0; 18];
+        // no image id or color map, true color
+        header[2] = if rle { 10 } else { 2 };
+        // the origin stays at (0, 0), the bottom left
+        header[12..14].copy_from_slice(&(width as u16).to_le_bytes());
+        header[14..16].copy_from_slice(&(height as u16).to_le_bytes());
+        header[16] = 24;
+        out.write_all(&header)?;
+
+        let mut bytes = vec![];
+        for row in self.bgr_rows_bottom_up() {
+            if rle {
+                encode_row(&row, &mut bytes);
+            } else {
+                bytes.extend_from_slice(&row);
+            }
+        }
+        out.write_all(&bytes)?;
+        out.write_all(FOOTER)?;
+        out.flush()
+    }
+}
+
+/// Run length encode a row of 3 byte pixels into `out`.
+///
+/// Two or more of the same pixel make a run packet, anything in between goes into raw packets.
+fn encode_row(row: &[u8], out: &mut Vec<u8>) {
+    let pixels: Vec<&[u8]> = row.chunks_exact(3).collect();
+    // how many times the pixel at i repeats, up to the size of a packet
+    let run = |i: usize| {
+        pixels[i..]
+            .iter()
+            .take(MAX_PACKET)
+            .take_while(|&&p| p == pixels[i])
+            .count()
+    };
+
+    let mut i = 0;
+    while i < pixels.len() {
+        let n = run(i);
+        if n > 1 {
+            out.push(0x80 | (n - 1) as u8);
+            out.extend_from_slice(pixels[i]);
+            i += n;
+        } else {
+            let start = i;
+            while i < pixels.len() && i - start < MAX_PACKET && run(i) == 1 {
+                i += 1;
+            }
+            out.push((i - start - 1) as u8);
+            for p in &pixels[start..i] {
+                out.extend_from_slice(p);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{Canvas, RGB};
+
+    #[test]
+    fn test_rle() {
+        let row: Vec<u8> = [1, 1, 1, 2, 3, 3, 4, 5, 6]
+            .iter()
+            .flat_map(|&p| vec![p, 0, 0])
+            .collect();
+        let mut out = vec![];
+        encode_row(&row, &mut out);
+        assert_eq!(
+            vec![0x82, 1, 0, 0, 0, 2, 0, 0, 0x81, 3, 0, 0, 2, 4, 0, 0, 5, 0, 0, 6, 0, 0],
+            out
+        );
+
+        // long runs are split into packets of 128 pixels
+        let row = vec![7; 3 * 300];
+        let mut out = vec![];
+        encode_row(&row, &mut out);
+        assert_eq!(vec![0xff, 7, 7, 7, 0xff, 7, 7, 7, 0xab, 7, 7, 7], out);
+
+        // and so are long stretches without runs
+        let row: Vec<u8> = (0..900).map(|i| (i / 3) as u8).collect();
+        let mut out = vec![];
+        encode_row(&row, &mut out);
+        let packets = [
+            &[0x7f][..],
+            &row[..384],
+            &[0x7f],
+            &row[384..768],
+            &[0x2b],
+            &row[768..],
+        ];
+        assert_eq!(packets.concat(), out);
+    }
+
+    #[test]
+    fn test_tga() {
+        let mut img = PPMImg::new(2, 2, 255);
+        img.invert_y = true;
+        img.set_fg_color(RGB::new(10, 20, 30));
+        img.plot(1, 0);
+
+        let mut raw = vec![];
+        img.write_tga_to(&mut raw, false).unwrap();
+        assert_eq!(2, raw[2]);
+        assert_eq!([2, 0, 2, 0, 24, 0], raw[12..18]);
+        // bottom row first
+        assert_eq!(
+            vec![0, 0, 0, 30, 20, 10, 0, 0, 0, 0, 0, 0],
+            raw[18..30].to_vec()
+        );
+        assert!(raw.ends_with(FOOTER));
+
+        let mut rle = vec![];
+        img.write_tga_to(&mut rle, true).unwrap();
+        assert_eq!(10, rle[2]);
+        assert_eq!(raw[3..18], rle[3..18]);
+        let pixels = &rle[18..rle.len() - FOOTER.len()];
+        assert_eq!(vec![1, 0, 0, 0, 30, 20, 10, 0x81, 0, 0, 0], pixels.to_vec());
+    }
+}
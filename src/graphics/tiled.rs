@@ -0,0 +1,348 @@
+//! Tiled rendering of polygon matrices on several threads
+//!
+//! The canvas is split into square tiles and every triangle is binned into the tiles its
+//! bounding box touches. Each tile is drawn on its own copy of its pixels, in parallel, and
+//! copied back afterwards. Tiles draw with the same code as the serial path and only keep the
+//! pixels inside of them, so the result is bit-identical to rendering serially.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use crate::graphics::{
+    canvas::{
+        average_samples, render_triangles, render_triangles_lit, to_samples, triangle_count,
+        Canvas, DrawMode,
+    },
+    lighting::{Lighting, Material, Shading},
+    matrix::Matrix,
+    Culling, LineMode, PPMImg, Stroke, RGB,
+};
+
+/// Extra pixels around the bounding box of a triangle that drawing it may touch,
+/// e.g. the neighboring pixels of anti-aliased edges
+const MARGIN: f64 = 2.;
+
+/// One rectangle of the samples of a canvas, drawn on its own
+struct Tile {
+    /// the samples of the tile, (0, 0) being sample (x, y) on the canvas
+    img: PPMImg,
+    x: i32,
+    y: i32,
+    /// samples per pixel of the canvas along each axis
+    samples: u32,
+    /// size of the whole canvas, in pixels
+    canvas_size: (u32, u32),
+}
+
+impl Tile {
+    /// Plot every sample of canvas pixel (x, y)
+    fn plot_block(&mut self, x: i32, y: i32, z: Option<f64>) {
+        let s = self.samples as i32;
+        for sy in 0..s {
+            for sx in 0..s {
+                self.plot_sample(x.saturating_mul(s) + sx, y.saturating_mul(s) + sy, z);
+            }
+        }
+    }
+
+    /// Position of canvas sample (x, y) within the tile, if it is in it
+    fn local(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (lx, ly) = (x - self.x, y - self.y);
+        if lx >= 0 && ly >= 0 && (lx as u32) < self.img.width && (ly as u32) < self.img.height {
+            Some((lx, ly))
+        } else {
+            None
+        }
+    }
+}
+
+impl Canvas for Tile {
+    fn plot(&mut self, x: i32, y: i32) {
+        self.plot_block(x, y, None);
+    }
+    fn plot_z(&mut self, x: i32, y: i32, z: f64) {
+        self.plot_block(x, y, Some(z));
+    }
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+    fn plot_sample(&mut self, x: i32, y: i32, z: Option<f64>) {
+        if let Some((x, y)) = self.local(x, y) {
+            self.img.plot_sample(x, y, z);
+        }
+    }
+    fn get_sample(&self, x: i32, y: i32) -> Option<RGB> {
+        let (x, y) = self.local(x, y)?;
+        self.img.get_sample(x, y)
+    }
+    fn plot_coverage(&mut self, x: i32, y: i32, z: Option<f64>, coverage: f64) {
+        if let Some((x, y)) = self.local(x, y) {
+            self.img.plot_coverage(x, y, z, coverage);
+        }
+    }
+    fn get_pixel(&self, x: i32, y: i32) -> Option<RGB> {
+        average_samples(self, x, y)
+    }
+    fn set_fg_color(&mut self, color: RGB) {
+        self.img.set_fg_color(color);
+    }
+    fn set_bg_color(&mut self, color: RGB) {
+        self.img.set_bg_color(color);
+    }
+    fn get_fg_color(&self) -> RGB {
+        self.img.get_fg_color()
+    }
+    fn get_bg_color(&self) -> RGB {
+        self.img.get_bg_color()
+    }
+    fn set_stroke(&mut self, stroke: Stroke) {
+        self.img.set_stroke(stroke);
+    }
+    fn get_stroke(&self) -> &Stroke {
+        self.img.get_stroke()
+    }
+    fn width(&self) -> u32 {
+        self.canvas_size.0
+    }
+    fn height(&self) -> u32 {
+        self.canvas_size.1
+    }
+    fn depth(&self) -> u16 {
+        self.img.depth()
+    }
+    fn line_mode(&self) -> LineMode {
+        self.img.line_mode()
+    }
+    fn culling(&self) -> Culling {
+        self.img.culling()
+    }
+    fn fill_bounds(&self) -> (i32, i32, i32, i32) {
+        let (w, h) = (self.img.width as i32, self.img.height as i32);
+        (self.x, self.x + w - 1, self.y, self.y + h - 1)
+    }
+}
+
+impl PPMImg {
+    /// Same as `render_polygon_matrix_with`, but rendered in `tile_size` by `tile_size` tiles
+    /// on all available threads
+    pub fn render_polygon_matrix_tiled(&mut self, m: &Matrix, mode: DrawMode, tile_size: u32) {
+        if self.x_wrap || self.y_wrap {
+            // wrapped pixels can land in any tile
+            self.render_polygon_matrix_with(m, mode);
+            return;
+        }
+        self.render_tiled(m, tile_size, |tile, tris| {
+            render_triangles(tile, m, tris.iter().copied(), mode)
+        });
+    }
+
+    /// Same as `render_polygon_matrix_lit`, but rendered in `tile_size` by `tile_size` tiles
+    /// on all available threads
+    pub fn render_polygon_matrix_lit_tiled(
+        &mut self,
+        m: &Matrix,
+        lighting: &Lighting,
+        material: &Material,
+        shading: Shading,
+        tile_size: u32,
+    ) {
+        if self.x_wrap || self.y_wrap {
+            // wrapped pixels can land in any tile
+            self.render_polygon_matrix_lit(m, lighting, material, shading);
+            return;
+        }
+        let normals = match shading {
+            Shading::Flat => vec![],
+            Shading::Gouraud | Shading::Phong => m.vertex_normals(),
+        };
+        self.render_tiled(m, tile_size, |tile, tris| {
+            let tris = tris.iter().copied();
+            render_triangles_lit(tile, m, tris, &normals, lighting, material, shading)
+        });
+    }
+
+    /// Bin the triangles of `m` into tiles, `draw` them on every tile in parallel and
+    /// copy the tiles back. Tiles without any triangles are left alone.
+    fn render_tiled(
+        &mut self,
+        m: &Matrix,
+        tile_size: u32,
+        draw: impl Fn(&mut Tile, &[usize]) + Sync,
+    ) {
+        assert!(tile_size > 0, "Tiles must not be empty");
+        let cols = self.width.div_ceil(tile_size);
+        let rows = self.height.div_ceil(tile_size);
+
+        // tiles are made of samples, triangles are in pixels
+        let s = self.samples as f64;
+        let margin = MARGIN * s;
+        let mut bins = vec![vec![]; (cols * rows) as usize];
+        for i in 0..triangle_count(m) {
+            let (mut xmin, mut xmax) = (f64::INFINITY, f64::NEG_INFINITY);
+            let (mut ymin, mut ymax) = (f64::INFINITY, f64::NEG_INFINITY);
+            for row in i * 3..i * 3 + 3 {
+                let (x, y) = (m.get(row, 0).unwrap(), m.get(row, 1).unwrap());
+                let (x, y) = (to_samples(s, x), to_samples(s, y));
+                xmin = xmin.min(x);
+                xmax = xmax.max(x);
+                ymin = ymin.min(y);
+                ymax = ymax.max(y);
+            }
+            if xmax + margin < 0. || ymax + margin < 0. {
+                // off the canvas
+                continue;
+            }
+            let tile_range = |min: f64, max: f64, count: u32| {
+                let first = ((min - margin).max(0.) / tile_size as f64) as u32;
+                let last = ((max + margin) / tile_size as f64).min((count - 1) as f64) as u32;
+                first..=last
+            };
+            for row in tile_range(ymin, ymax, rows) {
+                for col in tile_range(xmin, xmax, cols) {
+                    bins[(row * cols + col) as usize].push(i);
+                }
+            }
+        }
+
+        let mut tiles = Vec::with_capacity(bins.len());
+        for (i, bin) in bins.into_iter().enumerate() {
+            if bin.is_empty() {
+                continue;
+            }
+            let (x, y) = ((i as u32 % cols) * tile_size, (i as u32 / cols) * tile_size);
+            let w = tile_size.min(self.width - x);
+            let h = tile_size.min(self.height - y);
+            let mut img = PPMImg::new_with_bg(h, w, self.depth, self.bg_color);
+            img.line_mode = self.line_mode;
+            img.stroke = self.stroke.clone();
+            img.culling = self.culling;
+            img.fg_color = self.fg_color;
+            img.fg_alpha = self.fg_alpha;
+            for ly in 0..h {
+                for lx in 0..w {
+                    let from = self.index((x + lx) as i32, (y + ly) as i32).unwrap();
+                    let to = (ly * w + lx) as usize;
+                    img.data[to] = self.data[from];
+                    img.zbuf[to] = self.zbuf[from];
+                }
+            }
+            let tile = Tile {
+                img,
+                x: x as i32,
+                y: y as i32,
+                samples: self.samples,
+                canvas_size: self.output_size(),
+            };
+            tiles.push(Mutex::new((tile, bin)));
+        }
+
+        // threads take the next tile that no one is working on until all are done
+        let next = AtomicUsize::new(0);
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        thread::scope(|s| {
+            for _ in 0..threads.min(tiles.len()) {
+                s.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    match tiles.get(i) {
+                        Some(tile) => {
+                            let (tile, bin) = &mut *tile.lock().unwrap();
+                            draw(tile, bin);
+                        }
+                        None => break,
+                    }
+                });
+            }
+        });
+
+        for tile in tiles {
+            let (tile, _) = tile.into_inner().unwrap();
+            let (w, h) = (tile.img.width, tile.img.height);
+            for ly in 0..h {
+                for lx in 0..w {
+                    let to = self.index(tile.x + lx as i32, tile.y + ly as i32).unwrap();
+                    let from = (ly * w + lx) as usize;
+                    self.data[to] = tile.img.data[from];
+                    self.zbuf[to] = tile.img.zbuf[from];
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{
+        lighting::Light,
+        matrix::{transform, Surface},
+        shadow::ShadowSettings,
+        vector::Vec3,
+        Filter,
+    };
+
+    fn scene() -> Matrix {
+        let mut m = Matrix::new_polygon_matrix();
+        m.set_surface(Surface::Color(RGB::new(200, 40, 40)));
+        m.add_sphere((0., 0., 0.), 60.);
+        m.set_surface(Surface::Color(RGB::new(40, 200, 40)));
+        m.add_torus((0., 0., 0.), 15., 90.);
+        m.unset_surface();
+        m.add_box((-150., 150., 50.), 300., 300., 200.);
+        m * transform::rotatex(30.) * transform::rotatey(20.) * transform::mv(110., 95., 0.)
+    }
+
+    fn assert_same(serial: &PPMImg, tiled: &PPMImg) {
+        assert!(serial.data == tiled.data, "pixels differ");
+        assert!(serial.zbuf == tiled.zbuf, "depths differ");
+    }
+
+    #[test]
+    fn test_tiled_matches_serial() {
+        let m = scene();
+        for &mode in [DrawMode::Filled, DrawMode::Wireframe].iter() {
+            for &line_mode in [LineMode::Bresenham, LineMode::AntiAliased].iter() {
+                let new = || {
+                    let mut img = PPMImg::new(190, 230, 255);
+                    img.invert_y = true;
+                    img.line_mode = line_mode;
+                    img.fg_alpha = 180;
+                    img
+                };
+                let (mut serial, mut tiled) = (new(), new());
+
+                serial.render_polygon_matrix_with(&m, mode);
+                tiled.render_polygon_matrix_tiled(&m, mode, 32);
+                assert_same(&serial, &tiled);
+
+                // drawing again on top of what's there
+                serial.render_polygon_matrix_with(&m, mode);
+                tiled.render_polygon_matrix_tiled(&m, mode, 50);
+                assert_same(&serial, &tiled);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tiled_lit_matches_serial() {
+        let m = scene();
+        let mut lighting = Lighting::new(RGB::gray(50));
+        lighting.add_light(Light::Directional {
+            direction: Vec3(-1., -1., -1.),
+            color: RGB::gray(255),
+        });
+        lighting.cast_shadows(&m, ShadowSettings::new(128, 1., 1));
+
+        for &shading in [Shading::Flat, Shading::Gouraud, Shading::Phong].iter() {
+            let new = || PPMImg::new_supersampled(100, 120, 255, 2, Filter::Tent);
+            let (mut serial, mut tiled) = (new(), new());
+            serial.render_polygon_matrix_lit(&m, &lighting, &Material::default(), shading);
+            tiled.render_polygon_matrix_lit_tiled(&m, &lighting, &Material::default(), shading, 16);
+            assert_same(&serial, &tiled);
+        }
+    }
+}
@@ -1,16 +1,16 @@
 use std::fs::File;
-use std::io::BufWriter;
-use std::path::Path;
+use std::io::{self, BufWriter};
 
 use crate::graphics::canvas::Canvas;
 
-pub(crate) fn create_file(filepath: &str) -> BufWriter<File> {
-    let path = Path::new(filepath);
-    let display = path.display();
-    match File::create(&path) {
-        Err(why) => panic!("Could not create {}: {}", display, why),
-        Ok(file) => BufWriter::new(file),
-    }
+/// Create (or truncate) the file at `filepath` for buffered writing
+pub(crate) fn create_file(filepath: &str) -> io::Result<BufWriter<File>> {
+    File::create(filepath).map(BufWriter::new).map_err(|why| {
+        io::Error::new(
+            why.kind(),
+            format!("Could not create {}: {}", filepath, why),
+        )
+    })
 }
 
 pub(crate) fn polar_to_xy(mag: f64, angle_degrees: f64) -> (f64, f64) {
@@ -49,6 +49,8 @@ pub(crate) fn compute_hermite3_coef(p0: f64, p1: f64, r0: f64, r1: f64) -> (f64,
 use crate::graphics::{Matrix, PPMImg};
 use std::{fs, process::Command};
 
+/// Show `img` with ImageMagick's `display` (`imdisplay` on Windows), or on the terminal
+/// if there is no such viewer
 pub(crate) fn display_ppm(img: &PPMImg) {
     let tmpfile_name = "tmp.ppm";
     img.write_binary(tmpfile_name)
@@ -59,19 +61,28 @@ pub(crate) fn display_ppm(img: &PPMImg) {
     } else {
         Command::new("display")
     };
-    let mut display = cmd
+    match cmd
         // .arg("-flip")
         .arg(tmpfile_name)
         .spawn()
-        .unwrap();
-    let _result = display.wait().unwrap();
-    fs::remove_file(tmpfile_name).expect("Error removing tmp file");
+    {
+        Ok(mut display) => {
+            let _result = display.wait().unwrap();
+        }
+        Err(_) => {
+            img.print_terminal().expect("Error writing to terminal");
+        }
+    }
+    // other displays may have removed it already
+    let _ = fs::remove_file(tmpfile_name);
 }
 
 /// Convenience method to display an edge matrix for testing purposes
 pub(crate) fn display_edge_matrix(m: &Matrix, ndc: bool) {
     let mut img = PPMImg::new(500, 500, 225);
     if ndc {
+        // ndc has y pointing up
+        img.invert_y = true;
         img.render_ndc_edges_n1to1(m);
     } else {
         img.render_edge_matrix(m);
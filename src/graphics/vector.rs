@@ -1,11 +1,11 @@
 use std::ops;
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Vec3(pub f64, pub f64, pub f64);
 
 impl Vec3 {
     pub fn _dot(a: &Self, b: &Self) -> f64 {
-        a.0 * b.0 + a.1 * b.1 + a.2 + b.2
+        a.0 * b.0 + a.1 * b.1 + a.2 * b.2
     }
 
     pub fn _cross(a: &Self, b: &Self) -> Self {
@@ -19,7 +19,7 @@ impl Vec3 {
 
 impl Vec3 {
     pub fn dot(&self, other: Self) -> f64 {
-        self.0 * other.0 + self.1 * other.1 + self.2 + other.2
+        self.0 * other.0 + self.1 * other.1 + self.2 * other.2
     }
 
     pub fn cross(&self, other: Self) -> Self {
@@ -29,6 +29,21 @@ impl Vec3 {
             self.0 * other.1 - self.1 * other.0,
         )
     }
+
+    /// Length of the vector
+    pub fn mag(&self) -> f64 {
+        self.dot(*self).sqrt()
+    }
+
+    /// Returns a unit vector in the same direction, or the zero vector unchanged
+    pub fn normalized(&self) -> Self {
+        let mag = self.mag();
+        if mag == 0. {
+            *self
+        } else {
+            *self * (1. / mag)
+        }
+    }
 }
 
 impl ops::Mul for Vec3 {
@@ -51,3 +66,23 @@ impl ops::Add for Vec3 {
         Vec3(self.0 + rhs.0, self.1 + rhs.1, self.2 + rhs.2)
     }
 }
+
+impl ops::Mul<f64> for Vec3 {
+    type Output = Vec3;
+    fn mul(self, rhs: f64) -> Self::Output {
+        Vec3(self.0 * rhs, self.1 * rhs, self.2 * rhs)
+    }
+}
+
+impl ops::Neg for Vec3 {
+    type Output = Vec3;
+    fn neg(self) -> Self::Output {
+        Vec3(-self.0, -self.1, -self.2)
+    }
+}
+
+impl From<Vec3> for (f64, f64, f64) {
+    fn from(v: Vec3) -> Self {
+        (v.0, v.1, v.2)
+    }
+}
@@ -1,9 +1,9 @@
 mod graphics;
 
 use graphics::{
-    canvas::Canvas,
-    matrix::{projections, transform, Matrix},
-    PPMImg,
+    canvas::{Canvas, DrawMode},
+    matrix::{projections, transform, Matrix, Surface},
+    Filter, PPMImg, RGB,
 };
 
 // # compilation:
@@ -11,14 +11,21 @@ use graphics::{
 // convert -delay 10 img{1..9}.ppm img{8..2}.ppm perspectives.gif
 
 fn main() {
-    let mut img = PPMImg::new(500, 500, 225);
+    // render at 3x3 the resolution and filter down to smooth out the edges
+    let samples = 3;
+    let mut img = PPMImg::new_supersampled(500, 500, 225, samples, Filter::Tent);
+    img.invert_y = true;
     let total = 9;
     let mut mv = 150.;
     for i in 1..=total {
         let mut model = Matrix::new_polygon_matrix();
+        model.set_surface(Surface::Color(RGB::new(225, 90, 60)));
         model.add_sphere((130., 110., 90.), 120.);
+        model.set_surface(Surface::Color(RGB::new(70, 160, 225)));
         model.add_sphere((-130., 100., 90.), 120.);
+        model.set_surface(Surface::Color(RGB::new(225, 200, 60)));
         model.add_box((-60., -60., 50.), 90., 90., 90.);
+        model.set_surface(Surface::Color(RGB::new(110, 210, 110)));
         model.add_torus((-30., -335., 90.), 25., 200.);
 
         let step = 150.;
@@ -29,14 +36,14 @@ fn main() {
         };
 
         model *= Matrix::ident(4)
-            * transform::mv(0., 0., mv)
+            * transform::mv(0., 0., -mv)
             * transform::rotatey(10. * i as f64 - total as f64 * 5.);
 
-        // now apply perspective
-        let mut model = model * projections::perspective(90., 1., 1., 600.);
+        // now apply perspective, clipping away everything outside of the view volume before dividing
+        let mut model = (model * projections::perspective(90., 1., 1., 1200.)).clip_polygons();
         model.perspective_divide();
         model.ndc_n1to1_to_device(img.width() as f64, img.height() as f64);
-        img.render_polygon_matrix(&model);
+        img.render_polygon_matrix_tiled(&model, DrawMode::Wireframe, 128);
         img.write_binary(format!("img{}.ppm", i).as_str())
             .expect("Error writing to file");
         img.clear();